@@ -0,0 +1,84 @@
+use bellman_bignat::group::RsaGroup;
+use rug::integer::Order;
+use rug::Integer;
+use sha2::{Digest, Sha256};
+
+use crate::batch::multi_power;
+use crate::wesolowski;
+use crate::{Delay, TrapdoorVDF};
+
+// bits of the Fiat-Shamir coefficients alpha_i
+pub const COEFF_BITS: u32 = 128;
+
+// one Wesolowski proof for 'y_i = ±x_i^e % m' over all i, for a shared delay exponent 'e'.
+// Its size does not depend on the number of instances.
+//
+// The check is done in the quotient group by {1, -1}: in the RSA group 'm - y_i' would pass
+// whenever alpha_i is even, and the prover could grind the outputs for that. Each y_i must
+// be the canonical representative 'wesolowski::canonical(x_i^e)', which pins its sign.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AggregateProof {
+    pub pi: Integer,
+}
+
+// alpha_i = H(m, x_1..x_n, y_1..y_n, i), truncated to COEFF_BITS
+fn coefficients(g: &RsaGroup, inputs: &[Integer], outputs: &[Integer]) -> Vec<Integer> {
+    let mut hasher = Sha256::new();
+    wesolowski::absorb(&mut hasher, &g.m);
+    for v in inputs.iter().chain(outputs) {
+        wesolowski::absorb(&mut hasher, v);
+    }
+
+    (0..inputs.len() as u64)
+        .map(|i| {
+            let mut h = hasher.clone();
+            h.update(i.to_be_bytes());
+            let digest = h.finalize();
+            Integer::from_digits(&digest[..(COEFF_BITS / 8) as usize], Order::Msf)
+        })
+        .collect()
+}
+
+// (prod x_i^alpha_i, prod y_i^alpha_i): if every 'y_i = x_i^e' then 'Y = X^e'
+fn combine(g: &RsaGroup, inputs: &[Integer], outputs: &[Integer]) -> (Integer, Integer) {
+    let alphas = coefficients(g, inputs, outputs);
+    (multi_power(g, inputs, &alphas), multi_power(g, outputs, &alphas))
+}
+
+impl TrapdoorVDF {
+
+    // prove all 'outputs[i] = canonical(inputs[i]^e)' at once: the inputs are combined into X
+    // and the proof 'X^floor(e/l)' is evaluated once, with the trapdoor when it is known
+    pub fn prove_aggregate(&self, inputs: &[Integer], outputs: &[Integer], delay: &Delay) -> AggregateProof {
+        assert_eq!(inputs.len(), outputs.len());
+        let xs = self.exponent(delay);
+        let (x, y) = combine(&self.group, inputs, outputs);
+        let l = wesolowski::challenge(&self.group, &x, &y);
+
+        let pi = if self.has_trapdoor() {
+            self.trapdoor_exponent(&x, &l, &xs)
+        } else {
+            Self::rsa_exponent(&self.group, &x, &l, xs.iter())
+        };
+        AggregateProof {
+            pi: pi,
+        }
+    }
+
+    pub fn verify_aggregate(&self, inputs: &[Integer], outputs: &[Integer], delay: &Delay, proof: &AggregateProof) -> bool {
+        if inputs.len() != outputs.len() {
+            return false;
+        }
+        if outputs.iter().any(|y| wesolowski::canonical(&self.group, y) != *y) {
+            return false;
+        }
+        let xs = self.exponent(delay);
+        let (x, y) = combine(&self.group, inputs, outputs);
+
+        // 'pi^l * X^r == ±Y'
+        let l = wesolowski::challenge(&self.group, &x, &y);
+        let r = wesolowski::exponent_mod(&xs, &l);
+        let lhs = self.group.op(&self.group.power(&proof.pi, &l), &self.group.power(&x, &r));
+        wesolowski::canonical(&self.group, &lhs) == wesolowski::canonical(&self.group, &y)
+    }
+}
@@ -0,0 +1,119 @@
+use bellman_bignat::group::RsaGroup;
+use rayon::prelude::*;
+use rug::Integer;
+
+use crate::wesolowski::{self, CheckpointParams, CheckpointProver, Proof};
+use crate::{Delay, TrapdoorVDF};
+
+impl TrapdoorVDF {
+
+    // evaluate every input with the trapdoor on the rayon thread pool.
+    // The delay exponent is built and reduced once for the whole batch;
+    // run inside 'ThreadPool::install' to use a dedicated pool.
+    pub fn eval_batch_with_trapdoor(&self, inputs: &[Integer], delay: &Delay) -> Vec<Integer> {
+        let xs = self.exponent(delay);
+        let e = self.reduce(&Integer::from(1usize), &xs);
+
+        inputs
+            .par_iter()
+            .map(|b| self.power_reduced(b, &e))
+            .collect()
+    }
+
+    // same as 'eval_batch_with_trapdoor', with a Wesolowski proof for each output
+    pub fn prove_batch_with_trapdoor(&self, inputs: &[Integer], delay: &Delay) -> Vec<Proof> {
+        let xs = self.exponent(delay);
+        let e = self.reduce(&Integer::from(1usize), &xs);
+
+        inputs
+            .par_iter()
+            .map(|b| {
+                let y = self.power_reduced(b, &e);
+                let l = wesolowski::challenge(&self.group, b, &y);
+                let pi = self.power_reduced(b, &self.reduce(&l, &xs));
+                Proof {
+                    output: y,
+                    pi: pi,
+                }
+            })
+            .collect()
+    }
+
+    // same as 'prove_batch_with_trapdoor', with each output given by its canonical
+    // representative up to sign and the proof made for it, for 'wesolowski::verify_batch'.
+    // 'pi^l * x^r' is then the output or its negation.
+    pub fn prove_batch_quotient(&self, inputs: &[Integer], delay: &Delay) -> Vec<Proof> {
+        let xs = self.exponent(delay);
+        let e = self.reduce(&Integer::from(1usize), &xs);
+
+        inputs
+            .par_iter()
+            .map(|b| {
+                let y = wesolowski::canonical(&self.group, &self.power_reduced(b, &e));
+                let l = wesolowski::challenge(&self.group, b, &y);
+                let pi = self.power_reduced(b, &self.reduce(&l, &xs));
+                Proof {
+                    output: y,
+                    pi: pi,
+                }
+            })
+            .collect()
+    }
+
+    // evaluate every input without the trapdoor. The bases advance together through one
+    // left-to-right pass over the exponent bits, so the independent squarings of the batch
+    // are interleaved instead of running one long chain after another.
+    pub fn eval_multi(&self, inputs: &[Integer], delay: &Delay) -> Vec<Integer> {
+        let e = wesolowski::exponent(&self.exponent(delay));
+
+        interleaved_power(&self.group, inputs, &e)
+    }
+
+    // same as 'eval_multi', with the Wesolowski proofs computed from checkpoints kept
+    // during the evaluation pass, at most 'max_checkpoints' elements per input
+    pub fn prove_multi(&self, inputs: &[Integer], delay: &Delay, max_checkpoints: usize) -> Vec<Proof> {
+        let e = wesolowski::exponent(&self.exponent(delay));
+        let params = CheckpointParams::for_bits(e.significant_bits(), max_checkpoints);
+
+        CheckpointProver::eval_multi(&self.group, inputs, &e, params)
+            .par_iter()
+            .map(|prover| prover.prove())
+            .collect()
+    }
+}
+
+// compute 'bs[i]^e % m' for every i, squaring all accumulators once per exponent bit
+pub fn interleaved_power(g: &RsaGroup, bs: &[Integer], e: &Integer) -> Vec<Integer> {
+    let mut accs = vec![Integer::from(1usize); bs.len()];
+    for i in (0..e.significant_bits()).rev() {
+        let bit = e.get_bit(i);
+        for (acc, b) in accs.iter_mut().zip(bs) {
+            acc.square_mut();
+            *acc %= &g.m;
+            if bit {
+                *acc *= b;
+                *acc %= &g.m;
+            }
+        }
+    }
+    accs
+}
+
+// compute 'prod(bs[i]^es[i]) % m' with a single squaring chain shared by all the terms
+pub fn multi_power(g: &RsaGroup, bs: &[Integer], es: &[Integer]) -> Integer {
+    assert_eq!(bs.len(), es.len());
+    let bits = es.iter().map(|e| e.significant_bits()).max().unwrap_or(0);
+
+    let mut acc = Integer::from(1usize);
+    for i in (0..bits).rev() {
+        acc.square_mut();
+        acc %= &g.m;
+        for (b, e) in bs.iter().zip(es) {
+            if e.get_bit(i) {
+                acc *= b;
+                acc %= &g.m;
+            }
+        }
+    }
+    acc
+}
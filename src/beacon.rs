@@ -0,0 +1,162 @@
+use bellman_bignat::hash::Hasher;
+use rug::integer::Order;
+use rug::Integer;
+use sapling_crypto::bellman::pairing::ff::{PrimeField, PrimeFieldRepr};
+use sha2::{Digest, Sha256};
+
+use crate::wesolowski::{self, CheckpointParams, CheckpointProver, Proof};
+use crate::{Delay, TrapdoorVDF};
+
+// Randomness beacon: each round hashes the previous randomness and a fresh seed (e.g. a
+// commit-reveal aggregate or external entropy) to a group element, runs the delay on it and
+// publishes a digest of the VDF output. Whoever contributes last to the seed cannot predict
+// the output before the delay has passed, so cannot bias it by withholding.
+// The modulus must have no known trapdoor.
+//
+// In the RSA group both y and 'm - y' could be proven, giving the evaluator a choice of two
+// digests, so a round publishes the canonical output 'min(y, m - y)' and is checked in the
+// quotient group by {1, -1} ('wesolowski::verify_quotient').
+
+const DOMAIN: &[u8] = b"vdf_snark/beacon";
+
+// 32-byte digest of a VDF output in Z_m
+pub trait OutputDigest {
+    fn digest(&self, m: &Integer, y: &Integer) -> [u8; 32];
+}
+
+pub struct Sha256Digest;
+
+impl OutputDigest for Sha256Digest {
+    fn digest(&self, m: &Integer, y: &Integer) -> [u8; 32] {
+        let len = ((m.significant_bits() + 7) / 8) as usize;
+        let mut bytes = vec![0u8; len];
+        y.write_digits(&mut bytes, Order::Msf);
+
+        let mut hasher = Sha256::new();
+        hasher.update(DOMAIN);
+        hasher.update(&bytes);
+        hasher.finalize().into()
+    }
+}
+
+// digest with one of bellman-bignat's field hashers, e.g. 'Poseidon<Bn256>';
+// the output is split into CAPACITY-bit limbs, as many as the modulus needs, and the
+// digest is the hash masked to its low CAPACITY bits. The top '256 - CAPACITY' bits are
+// always zero (3 for Bn256), so take 'Sha256Digest' where 256 uniform bits are needed.
+pub struct PoseidonDigest<H: Hasher>(pub H);
+
+impl<H: Hasher> OutputDigest for PoseidonDigest<H> {
+    fn digest(&self, m: &Integer, y: &Integer) -> [u8; 32] {
+        let limb_bits = H::F::CAPACITY;
+        let n_limbs = (m.significant_bits() + limb_bits - 1) / limb_bits;
+        let limbs: Vec<H::F> = (0..n_limbs)
+            .map(|i| {
+                let limb = Integer::from(y >> (i * limb_bits)).keep_bits(limb_bits);
+                H::F::from_str(limb.to_string().as_str()).unwrap()
+            })
+            .collect();
+
+        let repr = self.0.hash(&limbs).into_repr();
+        let mut bytes = vec![0u8; 8 * repr.as_ref().len()];
+        repr.write_be(&mut bytes[..]).unwrap();
+
+        let mut out = [0u8; 32];
+        let n = bytes.len().min(32);
+        out[32 - n..].copy_from_slice(&bytes[bytes.len() - n..]);
+        for bit in limb_bits.min(256)..256 {
+            out[31 - (bit / 8) as usize] &= !(1u8 << (bit % 8));
+        }
+        out
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Round {
+    pub index: u64,
+    pub seed: Vec<u8>,
+    pub input: Integer,
+    pub proof: Proof,
+    pub randomness: [u8; 32],
+}
+
+// expand SHA-256 in counter mode to 128 bits more than the modulus and reduce
+pub fn hash_to_group(m: &Integer, previous: &[u8; 32], seed: &[u8]) -> Integer {
+    let blocks = (m.significant_bits() + 128 + 255) / 256;
+    let mut bytes = Vec::with_capacity(32 * blocks as usize);
+    for i in 0..blocks {
+        let mut hasher = Sha256::new();
+        hasher.update(DOMAIN);
+        hasher.update(i.to_be_bytes());
+        hasher.update(previous);
+        hasher.update((seed.len() as u64).to_be_bytes());
+        hasher.update(seed);
+        bytes.extend_from_slice(&hasher.finalize());
+    }
+    Integer::from_digits(&bytes, Order::Msf) % m
+}
+
+pub struct Beacon<'a, D: OutputDigest> {
+    vdf: &'a TrapdoorVDF,
+    delay: Delay,
+    digest: D,
+    // delay exponent, built once for every round
+    exponent: Integer,
+    params: CheckpointParams,
+    rounds: Vec<Round>,
+}
+
+impl<'a, D: OutputDigest> Beacon<'a, D> {
+
+    // rounds are proven from checkpoints, keeping at most 'max_checkpoints' group elements
+    pub fn new(vdf: &'a TrapdoorVDF, delay: Delay, digest: D, max_checkpoints: usize) -> Self {
+        let e = wesolowski::exponent(&vdf.exponent(&delay));
+        Self {
+            vdf: vdf,
+            delay: delay,
+            digest: digest,
+            params: CheckpointParams::for_bits(e.significant_bits(), max_checkpoints),
+            exponent: e,
+            rounds: Vec::new(),
+        }
+    }
+
+    pub fn rounds(&self) -> &[Round] {
+        &self.rounds
+    }
+
+    fn previous(&self) -> [u8; 32] {
+        self.rounds.last().map_or([0u8; 32], |r| r.randomness)
+    }
+
+    // run one round on 'seed'
+    pub fn next_round(&mut self, seed: &[u8]) -> &Round {
+        let x = hash_to_group(&self.vdf.group.m, &self.previous(), seed);
+        let proof = CheckpointProver::eval(&self.vdf.group, &x, &self.exponent, self.params).prove_quotient();
+        let randomness = self.digest.digest(&self.vdf.group.m, &proof.output);
+
+        self.rounds.push(Round {
+            index: self.rounds.len() as u64,
+            seed: seed.to_vec(),
+            input: x,
+            proof: proof,
+            randomness: randomness,
+        });
+        self.rounds.last().unwrap()
+    }
+}
+
+// check every round and its link to the previous one; returns the index of the first bad round
+pub fn verify_chain<D: OutputDigest>(vdf: &TrapdoorVDF, delay: &Delay, digest: &D, rounds: &[Round]) -> Result<(), u64> {
+    let mut previous = [0u8; 32];
+    for (i, r) in rounds.iter().enumerate() {
+        let ok = r.index == i as u64
+            && r.input == hash_to_group(&vdf.group.m, &previous, &r.seed)
+            && wesolowski::verify_quotient(&vdf.group, &r.input, &vdf.exponent(delay), &r.proof)
+            && r.randomness == digest.digest(&vdf.group.m, &r.proof.output);
+        if !ok {
+            return Err(i as u64);
+        }
+        previous = r.randomness;
+    }
+    Ok(())
+}
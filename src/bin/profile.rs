@@ -0,0 +1,27 @@
+extern crate vdf_snark;
+
+use std::env;
+
+use bellman_bignat::hash::hashes::Poseidon;
+use sapling_crypto::bellman::pairing::bn256::Bn256;
+use vdf_snark::params::CircuitParams;
+use vdf_snark::profile::{profile_circuits, profile_gadgets};
+use vdf_snark::wesolowski::CHALLENGE_BITS;
+
+// usage: profile [limb_width] [modulus_bits] [t]
+fn main() {
+    let args: Vec<usize> = env::args().skip(1).map(|a| a.parse().expect("expected a number")).collect();
+    let limb_width = args.first().copied().unwrap_or(32);
+    let modulus_bits = args.get(1).copied().unwrap_or(2048);
+    let t = args.get(2).copied().unwrap_or(4);
+    let params = CircuitParams::new(limb_width, modulus_bits, CHALLENGE_BITS as usize).expect("invalid limb width");
+
+    println!("limb width {}, {} limbs ({} bits), t = {}\n", params.limb_width, params.n_limbs, params.modulus_bits, t);
+    let hasher = Poseidon::<Bn256>::default();
+    for profile in profile_gadgets::<Bn256, _>(&hasher, &params).expect("synthesis failed") {
+        println!("{}", profile);
+    }
+    for profile in profile_circuits::<Bn256, _>(&hasher, &params, t).expect("synthesis failed") {
+        println!("{}", profile);
+    }
+}
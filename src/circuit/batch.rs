@@ -0,0 +1,141 @@
+use bellman_bignat::hash::circuit::CircuitHasher;
+use bellman_bignat::hash::Hasher;
+use rug::Integer;
+
+use super::wesolowski::{self, verify_proof, CircuitProof};
+use super::{alloc_nat, check_params, constant_nat, modulus_tag, nat_limbs, nat_to_fields};
+use super::{Circuit, ConstraintSystem, Engine, SynthesisError};
+use crate::keys::CircuitDescriptor;
+use crate::params::{CircuitParams, ParamsError};
+use crate::{Delay, TrapdoorVDF};
+
+// One SNARK for n VDF evaluations under the same modulus and delay, e.g. all the orders of a
+// batch auction. Both are built into the circuit along with n, and each (x, y, pi) is checked
+// with 'wesolowski::verify_proof'. The only public input is the Poseidon digest of the limbs
+// of x_1, y_1, .., x_n, y_n, so an on-chain verifier pays for one input whatever n is and
+// checks the pairs it holds against the digest.
+
+// digest of the (x, y) pairs, as the circuit computes it
+pub fn digest<H: Hasher>(hasher: &H, params: &CircuitParams, pairs: &[(Integer, Integer)]) -> H::F {
+    let mut inputs = Vec::with_capacity(2 * pairs.len() * params.n_limbs);
+    for (x, y) in pairs {
+        inputs.extend(nat_to_fields::<H::F>(x, params));
+        inputs.extend(nat_to_fields::<H::F>(y, params));
+    }
+    hasher.hash(&inputs)
+}
+
+#[derive(Clone)]
+pub struct BatchCircuit<E: Engine, H> {
+    pub hasher: H,
+    pub params: CircuitParams,
+    pub modulus: Integer,
+    pub delay: Delay,
+    // factors of the delay exponent
+    xs: Vec<Integer>,
+    pub n: usize,
+    // (x, proof) for each instance
+    pub instances: Option<Vec<(Integer, CircuitProof)>>,
+    pub digest: Option<E::Fr>,
+}
+
+impl<E, H> BatchCircuit<E, H>
+where
+    E: Engine,
+    H: Hasher<F = E::Fr> + CircuitHasher<E = E>,
+{
+
+    // circuit shape only, e.g. for parameter generation
+    pub fn blank(vdf: &TrapdoorVDF, delay: &Delay, hasher: H, params: CircuitParams, n: usize) -> Self {
+        Self {
+            hasher: hasher,
+            params: params,
+            modulus: vdf.group.m.clone(),
+            delay: delay.clone(),
+            xs: vdf.exponent(delay),
+            n: n,
+            instances: None,
+            digest: None,
+        }
+    }
+
+    // circuit with its witness for proofs made against the circuit challenge,
+    // e.g. by 'wesolowski::prove'
+    pub fn new(vdf: &TrapdoorVDF, delay: &Delay, hasher: H, params: CircuitParams, instances: Vec<(Integer, CircuitProof)>) -> Result<Self, ParamsError> {
+        let m = &vdf.group.m;
+        for (x, proof) in &instances {
+            params.check_element(m, x)?;
+            params.check_element(m, &proof.output)?;
+            params.check_element(m, &proof.pi)?;
+        }
+        let pairs: Vec<(Integer, Integer)> = instances.iter().map(|(x, p)| (x.clone(), p.output.clone())).collect();
+        let mut circuit = Self::blank(vdf, delay, hasher, params, instances.len());
+        circuit.digest = Some(digest(&circuit.hasher, &params, &pairs));
+        circuit.instances = Some(instances);
+        Ok(circuit)
+    }
+
+    // evaluate and prove 'delay' on every base, then build the circuit
+    pub fn prove(vdf: &TrapdoorVDF, delay: &Delay, hasher: H, params: CircuitParams, bases: &[Integer]) -> Result<Self, ParamsError> {
+        let instances = bases
+            .iter()
+            .map(|x| Ok((x.clone(), wesolowski::prove(vdf, delay, &hasher, &params, x)?)))
+            .collect::<Result<Vec<_>, ParamsError>>()?;
+        Self::new(vdf, delay, hasher, params, instances)
+    }
+
+    pub fn public_inputs(&self) -> Vec<E::Fr> {
+        vec![self.digest.expect("no witness")]
+    }
+
+    // the instance's value at 'pick', missing when synthesizing without a witness
+    fn value(&self, i: usize, pick: fn(&(Integer, CircuitProof)) -> &Integer) -> Option<Integer> {
+        self.instances.as_ref().map(|v| pick(&v[i]).clone())
+    }
+}
+
+impl<E, H> CircuitDescriptor<E> for BatchCircuit<E, H>
+where
+    E: Engine,
+    H: Hasher<F = E::Fr> + CircuitHasher<E = E>,
+{
+    type Circuit = Self;
+
+    // the modulus is part of the circuit, so its hash is part of the name
+    fn id(&self) -> String {
+        format!("batch-{}x{}-t{}-n{}-{}", self.params.limb_width, self.params.n_limbs, self.delay.time(), self.n, modulus_tag(&self.modulus))
+    }
+
+    fn blank(&self) -> Self {
+        Self {
+            instances: None,
+            digest: None,
+            ..self.clone()
+        }
+    }
+}
+
+impl<E, H> Circuit<E> for BatchCircuit<E, H>
+where
+    E: Engine,
+    H: Hasher<F = E::Fr> + CircuitHasher<E = E>,
+{
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        check_params::<E>(&self.params)?;
+        let modulus = constant_nat(cs.namespace(|| "modulus"), &self.modulus, self.params.limb_width, self.params.n_limbs)?;
+
+        let mut limbs = Vec::with_capacity(2 * self.n * self.params.n_limbs);
+        for i in 0..self.n {
+            let mut cs = cs.namespace(|| format!("instance {}", i));
+            let x = alloc_nat(cs.namespace(|| "x"), &self.value(i, |(x, _)| x), &self.params)?;
+            let y = alloc_nat(cs.namespace(|| "y"), &self.value(i, |(_, p)| &p.output), &self.params)?;
+            let pi = alloc_nat(cs.namespace(|| "pi"), &self.value(i, |(_, p)| &p.pi), &self.params)?;
+            verify_proof(cs.namespace(|| "verify"), &self.hasher, &self.params, &modulus, &self.xs, &x, &y, &pi)?;
+            limbs.extend(nat_limbs(cs.namespace(|| "x limbs"), &x)?);
+            limbs.extend(nat_limbs(cs.namespace(|| "y limbs"), &y)?);
+        }
+
+        let digest = self.hasher.allocate_hash(cs.namespace(|| "digest"), &limbs)?;
+        digest.inputize(cs.namespace(|| "digest input"))
+    }
+}
@@ -0,0 +1,34 @@
+use bellman_bignat::hash::circuit::CircuitHasher;
+use bellman_bignat::hash::helper::hash_to_pocklington_prime as native_hash_to_prime;
+use bellman_bignat::hash::hash_to_pocklington_prime;
+use bellman_bignat::hash::Hasher;
+use bellman_bignat::mp::bignat::BigNat;
+use rug::Integer;
+
+use super::{nat_limbs, nat_to_fields, ConstraintSystem, Engine, SynthesisError};
+use crate::params::{CircuitParams, ParamsError};
+
+// Fiat-Shamir challenge of Wesolowski proofs checked in circuits: a Pocklington-certified
+// prime with 'challenge_bits' bits of entropy, hashed from the limbs of x and y with the
+// circuit's field hasher. The native challenge (SHA-256 and 'next_prime') is far too costly
+// to check in a circuit, so proofs meant for a circuit are made against this one.
+
+pub fn challenge<H: Hasher>(hasher: &H, x: &Integer, y: &Integer, params: &CircuitParams) -> Result<Integer, ParamsError> {
+    let mut inputs = nat_to_fields(x, params);
+    inputs.extend(nat_to_fields(y, params));
+    native_hash_to_prime(&inputs, params.challenge_bits, hasher)
+        .map(|cert| cert.number)
+        .ok_or(ParamsError::NoChallengePrime)
+}
+
+// the challenge for allocated x and y
+pub fn alloc_challenge<E, H, CS>(mut cs: CS, hasher: &H, params: &CircuitParams, x: &BigNat<E>, y: &BigNat<E>) -> Result<BigNat<E>, SynthesisError>
+where
+    E: Engine,
+    H: Hasher<F = E::Fr> + CircuitHasher<E = E>,
+    CS: ConstraintSystem<E>,
+{
+    let mut inputs = nat_limbs(cs.namespace(|| "x limbs"), x)?;
+    inputs.extend(nat_limbs(cs.namespace(|| "y limbs"), y)?);
+    hash_to_pocklington_prime(cs.namespace(|| "hash to prime"), &inputs, params.limb_width, params.challenge_bits, hasher)
+}
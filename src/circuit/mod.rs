@@ -0,0 +1,152 @@
+// bellman circuits over 'bellman-bignat' gadgets
+
+pub mod batch;
+pub mod challenge;
+pub mod order;
+pub mod preimage;
+pub mod squaring;
+pub mod wesolowski;
+
+pub use sapling_crypto::bellman::pairing::Engine;
+pub use sapling_crypto::bellman::{Circuit, ConstraintSystem, SynthesisError};
+
+use bellman_bignat::mp::bignat::BigNat;
+use rug::integer::Order;
+use rug::Integer;
+use sapling_crypto::bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+use sapling_crypto::bellman::LinearCombination;
+use sapling_crypto::circuit::boolean::AllocatedBit;
+use sapling_crypto::circuit::num::AllocatedNum;
+
+use sha2::{Digest, Sha256};
+
+use crate::params::CircuitParams;
+
+// allocate 'value' as a BigNat, missing when synthesizing without a witness
+pub(crate) fn alloc_nat<E, CS>(cs: CS, value: &Option<Integer>, params: &CircuitParams) -> Result<BigNat<E>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    BigNat::alloc_from_nat(cs, || value.clone().ok_or(SynthesisError::AssignmentMissing), params.limb_width, params.n_limbs)
+}
+
+// 'value' as a BigNat whose limbs are fixed by the circuit, e.g. a modulus built into it
+pub(crate) fn constant_nat<E, CS>(mut cs: CS, value: &Integer, limb_width: usize, n_limbs: usize) -> Result<BigNat<E>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    let nat = BigNat::alloc_from_nat(cs.namespace(|| "alloc"), || Ok(value.clone()), limb_width, n_limbs)?;
+    for (i, limb) in nat.limbs.iter().enumerate() {
+        let c = Integer::from(value >> (i * limb_width) as u32).keep_bits(limb_width as u32);
+        let c = E::Fr::from_str(c.to_string().as_str()).unwrap();
+        cs.enforce(
+            || format!("limb {} constant", i),
+            |_| limb.clone(),
+            |lc| lc + CS::one(),
+            |lc| lc + (c, CS::one()),
+        );
+    }
+    Ok(nat)
+}
+
+// short hash of a modulus built into a circuit, to tell its keys apart
+pub(crate) fn modulus_tag(m: &Integer) -> String {
+    let hash = Sha256::digest(m.to_digits::<u8>(Order::Msf));
+    hash[..4].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// enforce 'a < m' by 'a + d + 1 = m' for a well-formed d
+pub(crate) fn assert_less<E, CS>(mut cs: CS, a: &BigNat<E>, m: &BigNat<E>, params: &CircuitParams) -> Result<(), SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    let d = a
+        .value
+        .as_ref()
+        .zip(m.value.as_ref())
+        .map(|(a, m)| (Integer::from(m - a) - 1u32).max(Integer::new()));
+    let d = alloc_nat(cs.namespace(|| "d"), &d, params)?;
+    d.assert_well_formed(cs.namespace(|| "d range"))?;
+    let one = constant_nat(cs.namespace(|| "one"), &Integer::from(1u32), params.limb_width, 1)?;
+    let sum = a.add::<CS>(&d)?.add::<CS>(&one)?;
+    sum.equal_when_carried_regroup(cs.namespace(|| "sum"), m)
+}
+
+// the layout must fit the circuit field before anything is allocated with it
+pub(crate) fn check_params<E: Engine>(params: &CircuitParams) -> Result<(), SynthesisError> {
+    params.check::<E::Fr>().map_err(|_| SynthesisError::Unsatisfiable)
+}
+
+// the limbs of 'n' as allocated numbers, e.g. to hash them
+pub(crate) fn nat_limbs<E, CS>(mut cs: CS, n: &BigNat<E>) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    let mut nums = Vec::with_capacity(n.limbs.len());
+    for (i, limb) in n.limbs.iter().enumerate() {
+        let value = n.limb_values.as_ref().map(|v| v[i]);
+        let num = AllocatedNum::alloc(cs.namespace(|| format!("limb {}", i)), || value.ok_or(SynthesisError::AssignmentMissing))?;
+        cs.enforce(
+            || format!("limb {} eq", i),
+            |lc| lc + num.get_variable(),
+            |lc| lc + CS::one(),
+            |_| limb.clone(),
+        );
+        nums.push(num);
+    }
+    Ok(nums)
+}
+
+// enforce 0 <= num < 2^bits; a bound above the field's capacity is not a range check and
+// is rejected
+pub(crate) fn fits_in_bits<E, CS>(mut cs: CS, num: &AllocatedNum<E>, bits: usize) -> Result<(), SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    if bits > E::Fr::CAPACITY as usize {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    let repr = num.get_value().map(|v| v.into_repr());
+    let mut sum = LinearCombination::<E>::zero();
+    let mut coeff = E::Fr::one();
+    for i in 0..bits {
+        let bit = AllocatedBit::alloc(
+            cs.namespace(|| format!("bit {}", i)),
+            repr.as_ref().map(|r| (r.as_ref()[i / 64] >> (i % 64)) & 1 == 1),
+        )?;
+        sum = sum + (coeff, bit.get_variable());
+        coeff.double();
+    }
+    cs.enforce(
+        || "recompose",
+        |lc| lc + num.get_variable(),
+        |lc| lc + CS::one(),
+        |_| sum,
+    );
+    Ok(())
+}
+
+// native counterpart of a BigNat's limbs
+pub(crate) fn nat_to_fields<F: PrimeField>(n: &Integer, params: &CircuitParams) -> Vec<F> {
+    (0..params.n_limbs)
+        .map(|i| {
+            let limb = Integer::from(n >> (i * params.limb_width) as u32).keep_bits(params.limb_width as u32);
+            F::from_str(limb.to_string().as_str()).unwrap()
+        })
+        .collect()
+}
+
+pub(crate) fn field_to_u64<F: PrimeField>(f: &F) -> Option<u64> {
+    let repr = f.into_repr();
+    let words = repr.as_ref();
+    if words[1..].iter().all(|w| *w == 0) {
+        Some(words[0])
+    } else {
+        None
+    }
+}
@@ -0,0 +1,353 @@
+use bellman_bignat::hash::circuit::CircuitHasher;
+use bellman_bignat::hash::Hasher;
+use bellman_bignat::mp::bignat::BigNat;
+use rand::RngCore;
+use rug::integer::Order as Digits;
+use rug::Integer;
+use sapling_crypto::bellman::pairing::ff::{Field, PrimeField};
+use sapling_crypto::circuit::num::AllocatedNum;
+
+use super::{alloc_nat, assert_less, check_params, constant_nat, field_to_u64, fits_in_bits, modulus_tag, nat_limbs, nat_to_fields};
+use super::{Circuit, ConstraintSystem, Engine, SynthesisError};
+use crate::homomorphic::PuzzleParams;
+use crate::keys::CircuitDescriptor;
+use crate::params::CircuitParams;
+use crate::timelock::TimelockError;
+use crate::{Delay, TrapdoorVDF};
+
+// Sealed orders that a SNARK can reason about.
+//
+// ChaCha20-Poly1305 is too costly inside a circuit, so these puzzles encrypt the order with a
+// Poseidon stream cipher. The puzzle is an RSW puzzle over public 'PuzzleParams' (g, h = g^e):
+// the trader picks a secret r, publishes the base 'x = g^r' and uses the VDF output
+// 'y = x^e = h^r'. The key is 'k = H(y)' for y in BigNat limbs, the pads are
+// 's_0 = H(k, H(x))' and 's_(i+1) = H(s_i, k)', and the ciphertext is 'm_i + s_i' for the
+// fields (price, size, side). The trader publishes 'c = H(k)' and proves, without revealing r
+// or the order, that 'x = g^r' and 'y = h^r', that 'k = H(y)' and 'c = H(k)', and that the
+// ciphertext decrypts under k to an order in range. Since y is fixed by x, even a trader who
+// knows the factors of the modulus cannot prove against another output, and whoever
+// evaluates the delay on x recovers the proven order. The modulus, g and h are built into the
+// circuit; their trapdoor must be unknown to the operator, who could otherwise open early.
+
+// bits of the sealing randomness, twice the security level against kangaroo attacks on 'x = g^r'
+pub const RANDOMNESS_BITS: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Order {
+    pub price: u64,
+    pub size: u64,
+    pub side: Side,
+}
+
+// price < 2^price_bits and 0 < size <= 2^size_bits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OrderBounds {
+    pub price_bits: usize,
+    pub size_bits: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrderPuzzle<F: PrimeField> {
+    pub modulus: Integer,
+    pub base: Integer,
+    pub delay: Delay,
+    pub key_commitment: F,
+    pub ciphertext: [F; 3],
+}
+
+impl Order {
+    pub(crate) fn fields<F: PrimeField>(&self) -> [F; 3] {
+        let side = match self.side {
+            Side::Buy => 0u64,
+            Side::Sell => 1u64,
+        };
+        [self.price, self.size, side].map(|v| F::from_str(v.to_string().as_str()).unwrap())
+    }
+
+    fn from_fields<F: PrimeField>(fields: &[F; 3]) -> Option<Self> {
+        let side = match field_to_u64(&fields[2])? {
+            0 => Side::Buy,
+            1 => Side::Sell,
+            _ => return None,
+        };
+        Some(Self {
+            price: field_to_u64(&fields[0])?,
+            size: field_to_u64(&fields[1])?,
+            side: side,
+        })
+    }
+}
+
+impl OrderBounds {
+    pub fn contains(&self, order: &Order) -> bool {
+        let fits = |v: u64, bits: usize| bits >= 64 || v >> bits == 0;
+        fits(order.price, self.price_bits) && order.size > 0 && fits(order.size - 1, self.size_bits)
+    }
+}
+
+fn derive_key<H: Hasher>(hasher: &H, y: &Integer, params: &CircuitParams) -> H::F {
+    hasher.hash(&nat_to_fields(y, params))
+}
+
+fn pads<H: Hasher>(hasher: &H, key: H::F, base: &Integer, params: &CircuitParams) -> [H::F; 3] {
+    let b = hasher.hash(&nat_to_fields(base, params));
+    let mut s = hasher.hash(&[key, b]);
+    let mut pads = [s; 3];
+    for pad in pads.iter_mut().skip(1) {
+        s = hasher.hash(&[s, key]);
+        *pad = s;
+    }
+    pads
+}
+
+fn randomness_limbs(params: &CircuitParams) -> usize {
+    (RANDOMNESS_BITS + params.limb_width - 1) / params.limb_width
+}
+
+// seal 'order' under 'puzzles'; returns the puzzle and the circuit, with its witness,
+// proving that the puzzle holds an order within 'bounds'
+pub fn seal_order<E, H>(puzzles: &PuzzleParams, hasher: &H, params: CircuitParams, bounds: OrderBounds, order: &Order) -> Result<(OrderPuzzle<E::Fr>, OrderCircuit<E, H>), TimelockError>
+where
+    E: Engine,
+    H: Hasher<F = E::Fr> + CircuitHasher<E = E>,
+{
+    let m = &puzzles.modulus;
+    params.check_modulus(m).map_err(TimelockError::Params)?;
+    let mut bytes = [0u8; RANDOMNESS_BITS / 8];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let r = Integer::from_digits(&bytes, Digits::Msf);
+    let x = puzzles.g.clone().pow_mod(&r, m).unwrap();
+    let y = puzzles.h.clone().pow_mod(&r, m).unwrap();
+
+    let key = derive_key(hasher, &y, &params);
+    let mut ciphertext = order.fields::<E::Fr>();
+    for (c, pad) in ciphertext.iter_mut().zip(pads(hasher, key, &x, &params)) {
+        c.add_assign(&pad);
+    }
+
+    let puzzle = OrderPuzzle {
+        modulus: m.clone(),
+        base: x,
+        delay: puzzles.delay.clone(),
+        key_commitment: hasher.hash(&[key]),
+        ciphertext: ciphertext,
+    };
+    let mut circuit = OrderCircuit::blank(puzzles, hasher.clone(), params, bounds);
+    circuit.base = Some(puzzle.base.clone());
+    circuit.key_commitment = Some(puzzle.key_commitment);
+    circuit.ciphertext = Some(puzzle.ciphertext);
+    circuit.randomness = Some(r);
+    circuit.order = Some(*order);
+    Ok((puzzle, circuit))
+}
+
+// recover the order by evaluating the delay sequentially
+pub fn open_order<H: Hasher>(puzzle: &OrderPuzzle<H::F>, hasher: &H, params: &CircuitParams) -> Option<Order> {
+    let vdf = TrapdoorVDF::setup("2", puzzle.modulus.to_string_radix(10).as_str());
+    let y = vdf.eval_delay(&puzzle.base, &puzzle.delay);
+    open_order_with_output(puzzle, hasher, params, &y)
+}
+
+// recover the order from an already computed VDF output; None if the key does not match
+// the commitment
+pub fn open_order_with_output<H: Hasher>(puzzle: &OrderPuzzle<H::F>, hasher: &H, params: &CircuitParams, y: &Integer) -> Option<Order> {
+    let key = derive_key(hasher, y, params);
+    if hasher.hash(&[key]) != puzzle.key_commitment {
+        return None;
+    }
+    let mut fields = puzzle.ciphertext;
+    for (m, pad) in fields.iter_mut().zip(pads(hasher, key, &puzzle.base, params)) {
+        m.sub_assign(&pad);
+    }
+    Order::from_fields(&fields)
+}
+
+// Public inputs: the base limbs, the key commitment and the three ciphertext fields.
+#[derive(Clone)]
+pub struct OrderCircuit<E: Engine, H> {
+    pub hasher: H,
+    pub params: CircuitParams,
+    pub bounds: OrderBounds,
+    pub puzzles: PuzzleParams,
+    pub base: Option<Integer>,
+    pub key_commitment: Option<E::Fr>,
+    pub ciphertext: Option<[E::Fr; 3]>,
+    // r with 'base = g^r'
+    pub randomness: Option<Integer>,
+    pub order: Option<Order>,
+}
+
+impl<E, H> OrderCircuit<E, H>
+where
+    E: Engine,
+    H: Hasher<F = E::Fr> + CircuitHasher<E = E>,
+{
+
+    // circuit shape only, e.g. for parameter generation
+    pub fn blank(puzzles: &PuzzleParams, hasher: H, params: CircuitParams, bounds: OrderBounds) -> Self {
+        Self {
+            hasher: hasher,
+            params: params,
+            bounds: bounds,
+            puzzles: puzzles.clone(),
+            base: None,
+            key_commitment: None,
+            ciphertext: None,
+            randomness: None,
+            order: None,
+        }
+    }
+
+    // public inputs of a proof about 'puzzle', in allocation order
+    pub fn public_inputs(puzzle: &OrderPuzzle<E::Fr>, params: &CircuitParams) -> Vec<E::Fr> {
+        let mut inputs = nat_to_fields(&puzzle.base, params);
+        inputs.push(puzzle.key_commitment);
+        inputs.extend_from_slice(&puzzle.ciphertext);
+        inputs
+    }
+}
+
+impl<E, H> CircuitDescriptor<E> for OrderCircuit<E, H>
+where
+    E: Engine,
+    H: Hasher<F = E::Fr> + CircuitHasher<E = E>,
+{
+    type Circuit = Self;
+
+    // the modulus, g and h are part of the circuit, so their hashes are part of the name
+    fn id(&self) -> String {
+        format!(
+            "order-{}x{}-p{}-s{}-t{}-{}-{}-{}",
+            self.params.limb_width,
+            self.params.n_limbs,
+            self.bounds.price_bits,
+            self.bounds.size_bits,
+            self.puzzles.delay.time(),
+            modulus_tag(&self.puzzles.modulus),
+            modulus_tag(&self.puzzles.g),
+            modulus_tag(&self.puzzles.h)
+        )
+    }
+
+    fn blank(&self) -> Self {
+        Self {
+            base: None,
+            key_commitment: None,
+            ciphertext: None,
+            randomness: None,
+            order: None,
+            ..self.clone()
+        }
+    }
+}
+
+impl<E, H> Circuit<E> for OrderCircuit<E, H>
+where
+    E: Engine,
+    H: Hasher<F = E::Fr> + CircuitHasher<E = E>,
+{
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        check_params::<E>(&self.params)?;
+        let base = alloc_nat(cs.namespace(|| "base"), &self.base, &self.params)?;
+        base.inputize(cs.namespace(|| "base input"))?;
+        let commitment = AllocatedNum::alloc(cs.namespace(|| "key commitment"), || {
+            self.key_commitment.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        commitment.inputize(cs.namespace(|| "key commitment input"))?;
+        let mut ciphertext = Vec::with_capacity(3);
+        for i in 0..3 {
+            let c = AllocatedNum::alloc(cs.namespace(|| format!("ciphertext {}", i)), || {
+                self.ciphertext.map(|c| c[i]).ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            c.inputize(cs.namespace(|| format!("ciphertext {} input", i)))?;
+            ciphertext.push(c);
+        }
+
+        // x = g^r % m and y = h^r % m, both reduced
+        let p = &self.puzzles;
+        let (limb_width, n_limbs) = (self.params.limb_width, self.params.n_limbs);
+        let modulus = constant_nat(cs.namespace(|| "modulus"), &p.modulus, limb_width, n_limbs)?;
+        let g = constant_nat(cs.namespace(|| "g"), &p.g, limb_width, n_limbs)?;
+        let h = constant_nat(cs.namespace(|| "h"), &p.h, limb_width, n_limbs)?;
+        let r = BigNat::alloc_from_nat(
+            cs.namespace(|| "r"),
+            || self.randomness.clone().ok_or(SynthesisError::AssignmentMissing),
+            limb_width,
+            randomness_limbs(&self.params),
+        )?;
+        let g_r = g.pow_mod(cs.namespace(|| "g^r"), &r, &modulus)?;
+        assert_less(cs.namespace(|| "base < N"), &base, &modulus, &self.params)?;
+        g_r.equal(cs.namespace(|| "base check"), &base)?;
+        let y = alloc_nat(cs.namespace(|| "y"), &self.randomness.as_ref().map(|r| p.h.clone().pow_mod(r, &p.modulus).unwrap()), &self.params)?;
+        let h_r = h.pow_mod(cs.namespace(|| "h^r"), &r, &modulus)?;
+        assert_less(cs.namespace(|| "y < N"), &y, &modulus, &self.params)?;
+        h_r.equal(cs.namespace(|| "y check"), &y)?;
+
+        // k = H(y), c = H(k)
+        let y_limbs = nat_limbs(cs.namespace(|| "y limbs"), &y)?;
+        let key = self.hasher.allocate_hash(cs.namespace(|| "hash output"), &y_limbs)?;
+        let hashed = self.hasher.allocate_hash(cs.namespace(|| "hash key"), &[key.clone()])?;
+        cs.enforce(
+            || "key commitment check",
+            |lc| lc + commitment.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + hashed.get_variable(),
+        );
+
+        // m_i + s_i = c_i
+        let limbs = nat_limbs(cs.namespace(|| "base limbs"), &base)?;
+        let b = self.hasher.allocate_hash(cs.namespace(|| "hash base"), &limbs)?;
+        let mut s = self.hasher.allocate_hash(cs.namespace(|| "pad 0"), &[key.clone(), b])?;
+        let fields = self.order.map(|o| o.fields::<E::Fr>());
+        let mut plaintext = Vec::with_capacity(3);
+        for i in 0..3 {
+            if i > 0 {
+                s = self.hasher.allocate_hash(cs.namespace(|| format!("pad {}", i)), &[s, key.clone()])?;
+            }
+            let m = AllocatedNum::alloc(cs.namespace(|| format!("plaintext {}", i)), || {
+                fields.map(|f| f[i]).ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            cs.enforce(
+                || format!("decrypt {}", i),
+                |lc| lc + m.get_variable() + s.get_variable(),
+                |lc| lc + CS::one(),
+                |lc| lc + ciphertext[i].get_variable(),
+            );
+            plaintext.push(m);
+        }
+
+        // price < 2^price_bits
+        fits_in_bits(cs.namespace(|| "price range"), &plaintext[0], self.bounds.price_bits)?;
+
+        // 0 <= size - 1 < 2^size_bits
+        let size = &plaintext[1];
+        let size_1 = AllocatedNum::alloc(cs.namespace(|| "size - 1"), || {
+            let mut v = size.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+            v.sub_assign(&E::Fr::one());
+            Ok(v)
+        })?;
+        cs.enforce(
+            || "size - 1 eq",
+            |lc| lc + size_1.get_variable() + CS::one(),
+            |lc| lc + CS::one(),
+            |lc| lc + size.get_variable(),
+        );
+        fits_in_bits(cs.namespace(|| "size range"), &size_1, self.bounds.size_bits)?;
+
+        // side is 0 or 1
+        let side = &plaintext[2];
+        cs.enforce(
+            || "side bit",
+            |lc| lc + side.get_variable(),
+            |lc| lc + CS::one() - side.get_variable(),
+            |lc| lc,
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+use bellman_bignat::hash::circuit::CircuitHasher;
+use bellman_bignat::hash::Hasher;
+use bellman_bignat::mp::bignat::BigNat;
+use rug::integer::Order as Digits;
+use rug::Integer;
+use sapling_crypto::bellman::pairing::ff::{Field, PrimeField};
+use sapling_crypto::bellman::LinearCombination;
+use sapling_crypto::circuit::num::AllocatedNum;
+
+use super::order::Order;
+use super::{alloc_nat, assert_less, check_params, constant_nat, modulus_tag, nat_to_fields};
+use super::{Circuit, ConstraintSystem, Engine, SynthesisError};
+use crate::keys::CircuitDescriptor;
+use crate::params::{CircuitParams, ParamsError};
+
+// VDF bases derived from a Poseidon commitment to an order.
+//
+// The trader commits to 'c = H(price, size, side, salt)' and the puzzle base is
+// 'order_base(c)': the pads 'h_0 = H(c)', 'h_(i+1) = H(h_i, c)' each give their low
+// 'limb_width * floor(CAPACITY / limb_width)' bits, which are concatenated to 128 bits more
+// than the modulus and reduced mod N, like 'beacon::hash_to_group' with SHA-256. The circuit
+// proves that the public base is derived this way from a hidden order and salt, so the VDF
+// and the committed order are linked without revealing the order before the delay.
+
+// bits over the modulus that make the reduction close to uniform
+const EXTRA_BITS: usize = 128;
+
+// the salt must be random, or the commitment to a small order space can be searched
+pub fn commit_order<H: Hasher>(hasher: &H, order: &Order, salt: H::F) -> H::F {
+    let mut inputs = order.fields::<H::F>().to_vec();
+    inputs.push(salt);
+    hasher.hash(&inputs)
+}
+
+// (limbs of the expansion, limbs taken from each pad)
+fn expansion_layout<F: PrimeField>(params: &CircuitParams) -> (usize, usize) {
+    let n_limbs = (params.modulus_bits + EXTRA_BITS + params.limb_width - 1) / params.limb_width;
+    (n_limbs, F::CAPACITY as usize / params.limb_width)
+}
+
+// the pads concatenated, before the reduction mod N
+fn expand<H: Hasher>(hasher: &H, params: &CircuitParams, c: H::F) -> Integer {
+    let (n_limbs, per_pad) = expansion_layout::<H::F>(params);
+    let bits = (per_pad * params.limb_width) as u32;
+    let mut wide = Integer::new();
+    let mut h = hasher.hash(&[c]);
+    for i in 0..(n_limbs + per_pad - 1) / per_pad {
+        if i > 0 {
+            h = hasher.hash(&[h, c]);
+        }
+        let repr = h.into_repr();
+        let pad = Integer::from_digits(repr.as_ref(), Digits::Lsf).keep_bits(bits);
+        wide += pad << (i as u32 * bits);
+    }
+    wide.keep_bits((n_limbs * params.limb_width) as u32)
+}
+
+// the layout is checked first, a limb wider than the field would leave no limbs per pad
+pub fn order_base<H: Hasher>(hasher: &H, params: &CircuitParams, m: &Integer, c: H::F) -> Result<Integer, ParamsError> {
+    params.check::<H::F>()?;
+    Ok(expand(hasher, params, c) % m)
+}
+
+// the base 'order_base(c)' for an allocated commitment c, reduced by 'modulus' and checked
+// to be below it, as 'red_mod' alone would also accept the residue plus the modulus
+pub fn alloc_order_base<E, H, CS>(mut cs: CS, hasher: &H, params: &CircuitParams, modulus: &BigNat<E>, c: &AllocatedNum<E>) -> Result<BigNat<E>, SynthesisError>
+where
+    E: Engine,
+    H: Hasher<F = E::Fr> + CircuitHasher<E = E>,
+    CS: ConstraintSystem<E>,
+{
+    let (n_limbs, per_pad) = expansion_layout::<E::Fr>(params);
+    let wide_value = c.get_value().map(|c| expand(hasher, params, c));
+    let wide = BigNat::alloc_from_nat(
+        cs.namespace(|| "expansion"),
+        || wide_value.clone().ok_or(SynthesisError::AssignmentMissing),
+        params.limb_width,
+        n_limbs,
+    )?;
+
+    // every limb of the expansion is packed from the bits of its pad
+    let mut h = hasher.allocate_hash(cs.namespace(|| "pad 0"), &[c.clone()])?;
+    for i in 0..(n_limbs + per_pad - 1) / per_pad {
+        if i > 0 {
+            h = hasher.allocate_hash(cs.namespace(|| format!("pad {}", i)), &[h, c.clone()])?;
+        }
+        let bits = h.into_bits_le_strict(cs.namespace(|| format!("pad {} bits", i)))?;
+        for j in 0..per_pad.min(n_limbs - i * per_pad) {
+            let mut packed = LinearCombination::<E>::zero();
+            let mut coeff = E::Fr::one();
+            for bit in &bits[j * params.limb_width..(j + 1) * params.limb_width] {
+                packed = packed + &bit.lc(CS::one(), coeff);
+                coeff.double();
+            }
+            cs.enforce(
+                || format!("pad {} limb {}", i, j),
+                |_| wide.limbs[i * per_pad + j].clone(),
+                |lc| lc + CS::one(),
+                |_| packed,
+            );
+        }
+    }
+    let base = wide.red_mod(cs.namespace(|| "expansion mod N"), modulus)?;
+    assert_less(cs.namespace(|| "base < N"), &base, modulus, params)?;
+    Ok(base)
+}
+
+// Public inputs: the base limbs and the order commitment. The modulus is built into the circuit.
+#[derive(Clone)]
+pub struct PreimageCircuit<E: Engine, H> {
+    pub hasher: H,
+    pub params: CircuitParams,
+    pub modulus: Integer,
+    pub base: Option<Integer>,
+    pub commitment: Option<E::Fr>,
+    pub order: Option<Order>,
+    pub salt: Option<E::Fr>,
+}
+
+impl<E, H> PreimageCircuit<E, H>
+where
+    E: Engine,
+    H: Hasher<F = E::Fr> + CircuitHasher<E = E>,
+{
+
+    // circuit shape only, e.g. for parameter generation
+    pub fn blank(hasher: H, params: CircuitParams, modulus: &Integer) -> Self {
+        Self {
+            hasher: hasher,
+            params: params,
+            modulus: modulus.clone(),
+            base: None,
+            commitment: None,
+            order: None,
+            salt: None,
+        }
+    }
+
+    // circuit with its witness; the base and commitment are derived from the order
+    pub fn new(hasher: H, params: CircuitParams, modulus: &Integer, order: &Order, salt: E::Fr) -> Result<Self, ParamsError> {
+        params.check::<E::Fr>()?;
+        params.check_modulus(modulus)?;
+        let c = commit_order(&hasher, order, salt);
+        let mut circuit = Self::blank(hasher, params, modulus);
+        circuit.base = Some(order_base(&circuit.hasher, &params, modulus, c)?);
+        circuit.commitment = Some(c);
+        circuit.order = Some(*order);
+        circuit.salt = Some(salt);
+        Ok(circuit)
+    }
+
+    // public inputs in allocation order
+    pub fn public_inputs(&self) -> Vec<E::Fr> {
+        let mut inputs = nat_to_fields(self.base.as_ref().expect("no witness"), &self.params);
+        inputs.push(self.commitment.expect("no witness"));
+        inputs
+    }
+}
+
+impl<E, H> CircuitDescriptor<E> for PreimageCircuit<E, H>
+where
+    E: Engine,
+    H: Hasher<F = E::Fr> + CircuitHasher<E = E>,
+{
+    type Circuit = Self;
+
+    fn id(&self) -> String {
+        format!("preimage-{}x{}-{}", self.params.limb_width, self.params.n_limbs, modulus_tag(&self.modulus))
+    }
+
+    fn blank(&self) -> Self {
+        Self::blank(self.hasher.clone(), self.params, &self.modulus)
+    }
+}
+
+impl<E, H> Circuit<E> for PreimageCircuit<E, H>
+where
+    E: Engine,
+    H: Hasher<F = E::Fr> + CircuitHasher<E = E>,
+{
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        check_params::<E>(&self.params)?;
+        let modulus = constant_nat(cs.namespace(|| "modulus"), &self.modulus, self.params.limb_width, self.params.n_limbs)?;
+        let base = alloc_nat(cs.namespace(|| "base"), &self.base, &self.params)?;
+        base.inputize(cs.namespace(|| "base input"))?;
+        let commitment = AllocatedNum::alloc(cs.namespace(|| "commitment"), || {
+            self.commitment.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        commitment.inputize(cs.namespace(|| "commitment input"))?;
+
+        // c = H(price, size, side, salt)
+        let fields = self.order.map(|o| o.fields::<E::Fr>());
+        let mut preimage = Vec::with_capacity(4);
+        for i in 0..3 {
+            preimage.push(AllocatedNum::alloc(cs.namespace(|| format!("order field {}", i)), || {
+                fields.map(|f| f[i]).ok_or(SynthesisError::AssignmentMissing)
+            })?);
+        }
+        preimage.push(AllocatedNum::alloc(cs.namespace(|| "salt"), || self.salt.ok_or(SynthesisError::AssignmentMissing))?);
+        let hashed = self.hasher.allocate_hash(cs.namespace(|| "commit"), &preimage)?;
+        cs.enforce(
+            || "commitment check",
+            |lc| lc + commitment.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + hashed.get_variable(),
+        );
+
+        // base = order_base(c)
+        let derived = alloc_order_base(cs.namespace(|| "order base"), &self.hasher, &self.params, &modulus, &commitment)?;
+        derived.equal(cs.namespace(|| "base check"), &base)
+    }
+}
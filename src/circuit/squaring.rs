@@ -0,0 +1,104 @@
+use bellman_bignat::mp::bignat::BigNat;
+use bellman_bignat::util::bench::ConstraintCounter;
+use rug::Integer;
+
+use super::{alloc_nat, assert_less, check_params, nat_to_fields, Circuit, ConstraintSystem, Engine, SynthesisError};
+use crate::keys::CircuitDescriptor;
+use crate::params::{CircuitParams, ParamsError};
+
+// Proves 'y = x^(2^t) % m' directly with t chained modular squarings, without a
+// Wesolowski proof. Each squaring is one 'BigNat::mult_mod', whose cost depends only on the
+// limb layout, so the constraint count grows linearly in t; it suits small t, test
+// instances, and statements that nest a short VDF.
+#[derive(Clone, Debug)]
+pub struct RepeatedSquaringCircuit {
+    pub params: CircuitParams,
+    // number of squarings, fixed when the circuit is built
+    pub t: usize,
+    pub modulus: Option<Integer>,
+    pub x: Option<Integer>,
+    pub y: Option<Integer>,
+}
+
+impl RepeatedSquaringCircuit {
+
+    // circuit with its witness: y is computed from x
+    pub fn new(params: CircuitParams, t: usize, modulus: &Integer, x: &Integer) -> Result<Self, ParamsError> {
+        params.check_element(modulus, x)?;
+        let mut y = x.clone();
+        for _ in 0..t {
+            y.square_mut();
+            y %= modulus;
+        }
+        Ok(Self {
+            params: params,
+            t: t,
+            modulus: Some(modulus.clone()),
+            x: Some(x.clone()),
+            y: Some(y),
+        })
+    }
+
+    // circuit shape only, e.g. for parameter generation
+    pub fn blank(params: CircuitParams, t: usize) -> Self {
+        Self {
+            params: params,
+            t: t,
+            modulus: None,
+            x: None,
+            y: None,
+        }
+    }
+
+    // public inputs in allocation order: modulus, x and y limbs
+    pub fn public_inputs<E: Engine>(&self) -> Vec<E::Fr> {
+        [&self.modulus, &self.x, &self.y]
+            .iter()
+            .flat_map(|v| nat_to_fields(v.as_ref().unwrap(), &self.params))
+            .collect()
+    }
+}
+
+impl<E: Engine> CircuitDescriptor<E> for RepeatedSquaringCircuit {
+    type Circuit = Self;
+
+    fn id(&self) -> String {
+        format!("squaring-{}x{}-t{}", self.params.limb_width, self.params.n_limbs, self.t)
+    }
+
+    fn blank(&self) -> Self {
+        Self::blank(self.params, self.t)
+    }
+}
+
+impl<E: Engine> Circuit<E> for RepeatedSquaringCircuit {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        check_params::<E>(&self.params)?;
+        let modulus = alloc_nat(cs.namespace(|| "modulus"), &self.modulus, &self.params)?;
+        let x = alloc_nat(cs.namespace(|| "x"), &self.x, &self.params)?;
+        let y = alloc_nat(cs.namespace(|| "y"), &self.y, &self.params)?;
+        modulus.inputize(cs.namespace(|| "modulus input"))?;
+        x.inputize(cs.namespace(|| "x input"))?;
+        y.inputize(cs.namespace(|| "y input"))?;
+
+        let mut acc: BigNat<E> = x.red_mod(cs.namespace(|| "x mod m"), &modulus)?;
+        for i in 0..self.t {
+            let (_, sq) = acc.mult_mod(cs.namespace(|| format!("square {}", i)), &acc, &modulus)?;
+            acc = sq;
+        }
+        // the residues of 'mult_mod' are only congruent, so y + m would pass without this
+        assert_less(cs.namespace(|| "y < m"), &y, &modulus, &self.params)?;
+        acc.equal(cs.namespace(|| "y check"), &y)
+    }
+}
+
+// constraints of the circuit for each t in 'ts', to see how the count grows with the delay
+pub fn constraint_growth<E: Engine>(params: CircuitParams, ts: &[usize]) -> Result<Vec<(usize, usize)>, SynthesisError> {
+    ts.iter()
+        .map(|&t| {
+            let mut cs = ConstraintCounter::new();
+            <RepeatedSquaringCircuit as Circuit<E>>::synthesize(RepeatedSquaringCircuit::blank(params, t), &mut cs)?;
+            Ok((t, cs.num_constraints()))
+        })
+        .collect()
+}
@@ -0,0 +1,105 @@
+use bellman_bignat::hash::circuit::CircuitHasher;
+use bellman_bignat::hash::Hasher;
+use bellman_bignat::mp::bignat::BigNat;
+use rug::Integer;
+
+use super::challenge::{alloc_challenge, challenge};
+use super::{assert_less, constant_nat, ConstraintSystem, Engine, SynthesisError};
+use crate::params::{CircuitParams, ParamsError};
+use crate::wesolowski::exponent_mod;
+use crate::{Delay, TrapdoorVDF};
+
+// Wesolowski verification in a circuit: 'pi^l * x^r = y' with 'r = prod(xs) % l', where l is
+// the circuit challenge of 'challenge::challenge'. The delay exponent 'prod(xs)' is fixed
+// when the circuit is built, so r is reduced from constants; 'TrapdoorVDF::exponent' repeats
+// one factor many times, and each run of equal factors costs one 'pow_mod' by its length.
+
+// Wesolowski proof that 'output = x^prod(xs) % m', with 'pi = x^floor(prod(xs)/l) % m' for the
+// circuit challenge l. It is its own type because 'crate::wesolowski::verify' rejects it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitProof {
+    pub output: Integer,
+    pub pi: Integer,
+}
+
+// equal consecutive factors of the exponent with their counts
+fn runs(xs: &[Integer]) -> Vec<(Integer, usize)> {
+    let mut runs: Vec<(Integer, usize)> = Vec::new();
+    for x in xs {
+        match runs.last_mut() {
+            Some((v, k)) if v == x => *k += 1,
+            _ => runs.push((x.clone(), 1)),
+        }
+    }
+    runs
+}
+
+// a constant in as few limbs as hold it
+fn small_constant<E, CS>(cs: CS, value: &Integer, params: &CircuitParams) -> Result<BigNat<E>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    let n_limbs = (value.significant_bits() as usize + params.limb_width - 1) / params.limb_width;
+    constant_nat(cs, value, params.limb_width, n_limbs.max(1))
+}
+
+// prove 'delay' for 'x' against the circuit challenge, with the trapdoor if 'vdf' has it
+pub fn prove<H: Hasher>(vdf: &TrapdoorVDF, delay: &Delay, hasher: &H, params: &CircuitParams, x: &Integer) -> Result<CircuitProof, ParamsError> {
+    let xs = vdf.exponent(delay);
+    let one = Integer::from(1usize);
+    let trapdoor = vdf.has_trapdoor();
+    let power = |l: &Integer| {
+        if trapdoor {
+            vdf.trapdoor_exponent(x, l, &xs)
+        } else {
+            TrapdoorVDF::rsa_exponent(&vdf.group, x, l, xs.iter())
+        }
+    };
+
+    let y = power(&one);
+    let l = challenge(hasher, x, &y, params)?;
+    Ok(CircuitProof {
+        pi: power(&l),
+        output: y,
+    })
+}
+
+// native counterpart of 'verify_proof'; false if no challenge prime is found
+pub fn verify<H: Hasher>(modulus: &Integer, xs: &[Integer], hasher: &H, params: &CircuitParams, x: &Integer, proof: &CircuitProof) -> bool {
+    let l = match challenge(hasher, x, &proof.output, params) {
+        Ok(l) => l,
+        Err(_) => return false,
+    };
+    let r = exponent_mod(xs, &l);
+    let lhs = proof.pi.clone().pow_mod(&l, modulus).unwrap() * x.clone().pow_mod(&r, modulus).unwrap() % modulus;
+    lhs == proof.output
+}
+
+// enforce that 'proof' (pi, y) shows 'y = x^prod(xs) % modulus' with y reduced
+pub fn verify_proof<E, H, CS>(mut cs: CS, hasher: &H, params: &CircuitParams, modulus: &BigNat<E>, xs: &[Integer], x: &BigNat<E>, y: &BigNat<E>, pi: &BigNat<E>) -> Result<(), SynthesisError>
+where
+    E: Engine,
+    H: Hasher<F = E::Fr> + CircuitHasher<E = E>,
+    CS: ConstraintSystem<E>,
+{
+    let l = alloc_challenge(cs.namespace(|| "challenge"), hasher, params, x, y)?;
+
+    // r = prod(c^k) % l over the runs (c, k) of xs
+    let mut r = small_constant(cs.namespace(|| "one"), &Integer::from(1usize), params)?;
+    for (i, (c, k)) in runs(xs).iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("run {}", i));
+        let c = small_constant(cs.namespace(|| "factor"), c, params)?;
+        let c = c.red_mod(cs.namespace(|| "factor mod l"), &l)?;
+        let k = small_constant(cs.namespace(|| "count"), &Integer::from(*k), params)?;
+        let ck = c.pow_mod(cs.namespace(|| "power"), &k, &l)?;
+        let (_, acc) = r.mult_mod(cs.namespace(|| "accumulate"), &ck, &l)?;
+        r = acc;
+    }
+
+    let pi_l = pi.pow_mod(cs.namespace(|| "pi^l"), &l, modulus)?;
+    let x_r = x.pow_mod(cs.namespace(|| "x^r"), &r, modulus)?;
+    let (_, lhs) = pi_l.mult_mod(cs.namespace(|| "pi^l * x^r"), &x_r, modulus)?;
+    assert_less(cs.namespace(|| "y range"), y, modulus, params)?;
+    lhs.equal(cs.namespace(|| "y check"), y)
+}
@@ -0,0 +1,127 @@
+use rand::RngCore;
+use rug::Integer;
+use sha2::{Digest, Sha256};
+
+use crate::timelock::{derive_key, header, random_element_with, TimelockError};
+use crate::wesolowski::{self, Proof};
+use crate::{Delay, TrapdoorVDF};
+
+// bytes of the salt masked together with the message
+const SALT_LEN: usize = 32;
+
+// Timed commitment. 'salt || message' is masked with a keystream derived from 'z = x^e % N'
+// for the delay exponent 'e', up to sign, and 'binding = SHA-256(header || salt || message)'
+// is published with it.
+//
+// The VDF proof does not bind the message: the committer knows φ(N) and can prove any z', and
+// so make the ciphertext decode to another message. The binding hash does: an opening is only
+// accepted when the decoded salt and message hash to it, so the committer's opening and
+// forcing the opening (computing z sequentially) give the same message or none. The salt
+// keeps a guessable message hidden until then.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Commitment {
+    pub modulus: Integer,
+    pub base: Integer,
+    pub delay: Delay,
+    pub binding: [u8; 32],
+    pub ciphertext: Vec<u8>,
+}
+
+// proof of 'z = x^e % N': given instantly by the committer with the trapdoor, or produced by
+// anyone through 'force_open'. The key is derived from 'canonical(z)', so z and 'N - z' open
+// the same way.
+pub type Opening = Proof;
+
+// XOR 'data' with the keystream 'SHA-256(key || i)', i = 0, 1, ..
+fn mask(key: &[u8; 32], data: &[u8]) -> Vec<u8> {
+    data.chunks(32)
+        .enumerate()
+        .flat_map(|(i, chunk)| {
+            let block = Sha256::new()
+                .chain_update(key)
+                .chain_update((i as u64).to_be_bytes())
+                .finalize();
+            chunk.iter().zip(block).map(|(d, k)| d ^ k).collect::<Vec<u8>>()
+        })
+        .collect()
+}
+
+impl Commitment {
+
+    fn vdf(&self) -> TrapdoorVDF {
+        TrapdoorVDF::setup("2", self.modulus.to_string_radix(10).as_str())
+    }
+
+    fn aad(&self) -> Vec<u8> {
+        header(&self.modulus, &self.base, &self.delay)
+    }
+
+    fn bind(&self, salted: &[u8]) -> [u8; 32] {
+        Sha256::new().chain_update(self.aad()).chain_update(salted).finalize().into()
+    }
+
+    // check that the commitment can be force-opened: the base is a unit of Z_N
+    pub fn verify(&self) -> bool {
+        self.base > 1 && self.base < self.modulus && Integer::from(self.base.gcd_ref(&self.modulus)) == 1
+    }
+
+    // check an opening against the commitment and decode the message. A decoded message that
+    // does not match 'binding' is rejected even when the proof verifies.
+    pub fn open(&self, opening: &Opening) -> Result<Vec<u8>, TimelockError> {
+        let vdf = self.vdf();
+        if !vdf.verify_delay(&self.base, &self.delay, opening) {
+            return Err(TimelockError::InvalidProof);
+        }
+        let z = wesolowski::canonical(&vdf.group, &opening.output);
+        let key = derive_key(&self.modulus, &z, &self.aad());
+        let salted = mask(&key, &self.ciphertext);
+        if salted.len() < SALT_LEN || self.bind(&salted) != self.binding {
+            return Err(TimelockError::Decrypt);
+        }
+        Ok(salted[SALT_LEN..].to_vec())
+    }
+
+    // open without the committer by evaluating the delay sequentially.
+    // The returned opening lets anyone check the result with 'open'; a 'Decrypt' error with a
+    // valid opening shows that the committer bound no message.
+    pub fn force_open(&self) -> Result<(Vec<u8>, Opening), TimelockError> {
+        if !self.verify() {
+            return Err(TimelockError::InvalidProof);
+        }
+        let opening = self.vdf().prove_delay(&self.base, &self.delay);
+        let message = self.open(&opening)?;
+        Ok((message, opening))
+    }
+}
+
+// commit to 'message' for 'delay'; returns the commitment and the committer's instant opening
+pub fn commit(vdf: &TrapdoorVDF, delay: &Delay, message: &[u8]) -> Result<(Commitment, Opening), TimelockError> {
+    commit_with_rng(vdf, delay, message, &mut rand::thread_rng())
+}
+
+// same as 'commit' with the base drawn from 'rng'
+pub fn commit_with_rng<R: RngCore>(vdf: &TrapdoorVDF, delay: &Delay, message: &[u8], rng: &mut R) -> Result<(Commitment, Opening), TimelockError> {
+    if !vdf.has_trapdoor() {
+        return Err(TimelockError::MissingTrapdoor);
+    }
+    let m = &vdf.group.m;
+    let x = random_element_with(m, rng);
+    let opening = vdf.prove_delay_with_trapdoor(&x, delay);
+
+    let mut salted = vec![0u8; SALT_LEN];
+    rng.fill_bytes(&mut salted);
+    salted.extend_from_slice(message);
+
+    let mut c = Commitment {
+        modulus: m.clone(),
+        base: x,
+        delay: delay.clone(),
+        binding: [0u8; 32],
+        ciphertext: Vec::new(),
+    };
+    c.binding = c.bind(&salted);
+    let key = derive_key(m, &wesolowski::canonical(&vdf.group, &opening.output), &c.aad());
+    c.ciphertext = mask(&key, &salted);
+
+    Ok((c, opening))
+}
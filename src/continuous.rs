@@ -0,0 +1,99 @@
+use rug::Integer;
+
+use crate::wesolowski::{self, CheckpointParams, CheckpointProver, Proof};
+use crate::TrapdoorVDF;
+
+// position on the chain: 'output = seed^(2^(index * delta)) % m'
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainState {
+    pub index: u64,
+    pub output: Integer,
+}
+
+// emitted every 'delta' squarings: 'proof.output = previous^(2^delta) % m'
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub index: u64,
+    pub proof: Proof,
+}
+
+impl Segment {
+    pub fn output(&self) -> &Integer {
+        &self.proof.output
+    }
+}
+
+impl ChainState {
+
+    // check that 'segment' is the next step after this state; a verifier can start
+    // from any emitted output and follow the chain from there
+    pub fn verify_next(&self, vdf: &TrapdoorVDF, delta: u32, segment: &Segment) -> bool {
+        segment.index == self.index + 1
+            && wesolowski::verify(&vdf.group, &self.output, &[Integer::from(1usize) << delta], &segment.proof)
+    }
+
+    // move to 'segment' if it verifies
+    pub fn advance(&mut self, vdf: &TrapdoorVDF, delta: u32, segment: &Segment) -> bool {
+        if !self.verify_next(vdf, delta, segment) {
+            return false;
+        }
+        self.index = segment.index;
+        self.output = segment.proof.output.clone();
+        true
+    }
+}
+
+// walks one sequential squaring chain in the group of 'vdf' and emits a proven
+// output every 'delta' squarings
+pub struct ContinuousVDF<'a> {
+    vdf: &'a TrapdoorVDF,
+    delta: u32,
+    params: CheckpointParams,
+    state: ChainState,
+}
+
+impl<'a> ContinuousVDF<'a> {
+
+    pub fn new(vdf: &'a TrapdoorVDF, seed: &Integer, delta: u32, max_checkpoints: usize) -> Self {
+        let state = ChainState {
+            index: 0,
+            output: Integer::from(seed % &vdf.group.m),
+        };
+        Self::resume(vdf, state, delta, max_checkpoints)
+    }
+
+    pub fn resume(vdf: &'a TrapdoorVDF, state: ChainState, delta: u32, max_checkpoints: usize) -> Self {
+        Self {
+            vdf: vdf,
+            delta: delta,
+            params: CheckpointParams::for_bits(delta + 1, max_checkpoints),
+            state: state,
+        }
+    }
+
+    pub fn state(&self) -> &ChainState {
+        &self.state
+    }
+
+    pub fn step(&mut self) -> Segment {
+        let e = Integer::from(1usize) << self.delta;
+        let proof = CheckpointProver::eval(&self.vdf.group, &self.state.output, &e, self.params).prove();
+
+        self.state = ChainState {
+            index: self.state.index + 1,
+            output: proof.output.clone(),
+        };
+        Segment {
+            index: self.state.index,
+            proof: proof,
+        }
+    }
+}
+
+impl<'a> Iterator for ContinuousVDF<'a> {
+    type Item = Segment;
+
+    fn next(&mut self) -> Option<Segment> {
+        Some(self.step())
+    }
+}
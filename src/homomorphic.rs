@@ -0,0 +1,123 @@
+use rug::Integer;
+
+use crate::timelock::{random_element, TimelockError};
+use crate::wesolowski::Proof;
+use crate::{Delay, TrapdoorVDF};
+
+// Malavolta-Thyagarajan linearly homomorphic time-lock puzzles over the modulus of a
+// 'TrapdoorVDF': 'h = g^e % N' for the delay exponent 'e', and secrets live in Z_N,
+// encoded Paillier-style as '(1+N)^s % N^2'.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PuzzleParams {
+    pub modulus: Integer,
+    pub g: Integer,
+    pub h: Integer,
+    pub delay: Delay,
+}
+
+// '(u, v) = (g^r % N, h^(r*N) * (1+N)^s % N^2)'
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Puzzle {
+    pub u: Integer,
+    pub v: Integer,
+}
+
+impl PuzzleParams {
+
+    // 'h' is computed quickly with the trapdoor; anyone can generate puzzles afterwards
+    pub fn setup(vdf: &TrapdoorVDF, delay: &Delay) -> Result<Self, TimelockError> {
+        if !vdf.has_trapdoor() {
+            return Err(TimelockError::MissingTrapdoor);
+        }
+        let n = &vdf.group.m;
+
+        // g = -g'^2, a generator of the squares times -1
+        let g_ = random_element(n);
+        let g = (n - Integer::from(g_.square_ref()) % n) % n;
+        let h = vdf.eval_delay_with_trapdoor(&g, delay);
+
+        Ok(Self {
+            modulus: n.clone(),
+            g: g,
+            h: h,
+            delay: delay.clone(),
+        })
+    }
+
+    fn modulus_squared(&self) -> Integer {
+        Integer::from(self.modulus.square_ref())
+    }
+
+    // lock a secret 's' in Z_N
+    pub fn generate(&self, s: &Integer) -> Puzzle {
+        self.generate_with(s, &random_element(&self.modulus_squared()))
+    }
+
+    // same as 'generate' with the randomness 'r' given; revealing 'r' and 's' lets anyone
+    // recompute the puzzle without the delay or a proof
+    pub fn generate_with(&self, s: &Integer, r: &Integer) -> Puzzle {
+        let n = &self.modulus;
+        let n2 = self.modulus_squared();
+
+        let u = self.g.clone().pow_mod(r, n).unwrap();
+        let h_rn = self.h.clone().pow_mod(&Integer::from(r * n), &n2).unwrap();
+        // (1+N)^s = 1 + s*N mod N^2
+        let enc = (Integer::from(s * n) + 1u32) % &n2;
+        Puzzle {
+            u: u,
+            v: h_rn * enc % &n2,
+        }
+    }
+
+    // puzzle of the sum of the secrets of 'a' and 'b', mod N
+    pub fn add(&self, a: &Puzzle, b: &Puzzle) -> Puzzle {
+        Puzzle {
+            u: Integer::from(&a.u * &b.u) % &self.modulus,
+            v: Integer::from(&a.v * &b.v) % self.modulus_squared(),
+        }
+    }
+
+    pub fn sum(&self, puzzles: &[Puzzle]) -> Puzzle {
+        puzzles.iter().fold(
+            Puzzle {
+                u: Integer::from(1usize),
+                v: Integer::from(1usize),
+            },
+            |acc, z| self.add(&acc, z),
+        )
+    }
+
+    fn vdf(&self) -> TrapdoorVDF {
+        TrapdoorVDF::setup("2", self.modulus.to_string_radix(10).as_str())
+    }
+
+    // 's = (v / w^N % N^2 - 1) / N' for 'w = h^r = u^e % N'
+    fn reveal(&self, puzzle: &Puzzle, w: &Integer) -> Option<Integer> {
+        let n2 = self.modulus_squared();
+        let w_n = w.clone().pow_mod(&self.modulus, &n2).unwrap();
+        let enc = Integer::from(&puzzle.v * &w_n.invert(&n2).ok()?) % &n2 - 1u32;
+        if !enc.is_divisible(&self.modulus) {
+            return None;
+        }
+        Some(enc / &self.modulus)
+    }
+
+    // recover the secret by evaluating the delay on 'u' sequentially.
+    // Returns None for a malformed puzzle.
+    pub fn solve(&self, puzzle: &Puzzle) -> Option<Integer> {
+        let w = self.vdf().eval_delay(&puzzle.u, &self.delay);
+        self.reveal(puzzle, &w)
+    }
+
+    // same as 'solve', with a proof of 'w = u^e' so the revealed secret can be audited
+    pub fn solve_with_proof(&self, puzzle: &Puzzle) -> Option<(Integer, Proof)> {
+        let proof = self.vdf().prove_delay(&puzzle.u, &self.delay);
+        let s = self.reveal(puzzle, &proof.output)?;
+        Some((s, proof))
+    }
+
+    pub fn verify_solution(&self, puzzle: &Puzzle, s: &Integer, proof: &Proof) -> bool {
+        self.vdf().verify_delay(&puzzle.u, &self.delay, proof)
+            && self.reveal(puzzle, &proof.output).as_ref() == Some(s)
+    }
+}
@@ -0,0 +1,144 @@
+use sapling_crypto::bellman::pairing::ff::{PrimeField, PrimeFieldRepr};
+use sapling_crypto::bellman::{Index, LinearCombination, Variable};
+use sha2::{Digest, Sha256};
+
+use crate::circuit::{Circuit, ConstraintSystem, Engine, SynthesisError};
+
+// Groth16 key management.
+//
+// Every circuit describes the shape that keys are generated for, identified by its id and
+// checked by a hash of its constraints. The on-disk 'KeyStore' is behind the 'keys' feature.
+
+#[cfg(feature = "keys")]
+mod store;
+
+#[cfg(feature = "keys")]
+pub use store::{verify, KeyError, KeyStore};
+
+// a circuit whose blank instance fixes the shape that keys are generated for
+pub trait CircuitDescriptor<E: Engine> {
+    type Circuit: Circuit<E>;
+
+    // stable identifier of the circuit and its build parameters, used as the file name
+    fn id(&self) -> String;
+
+    fn blank(&self) -> Self::Circuit;
+}
+
+// sizes of the constraint system and a hash of its constraints
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitShape {
+    pub inputs: u64,
+    pub aux: u64,
+    pub constraints: u64,
+    pub digest: [u8; 32],
+}
+
+impl CircuitShape {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(56);
+        for n in [self.inputs, self.aux, self.constraints] {
+            bytes.extend_from_slice(&n.to_be_bytes());
+        }
+        bytes.extend_from_slice(&self.digest);
+        bytes
+    }
+}
+
+// constraint system that only records the structure of a circuit
+struct ShapeCs {
+    inputs: u64,
+    aux: u64,
+    constraints: u64,
+    hasher: Sha256,
+}
+
+impl ShapeCs {
+    fn absorb<E: Engine>(&mut self, lc: &LinearCombination<E>) {
+        self.hasher.update((lc.as_ref().len() as u64).to_be_bytes());
+        for (var, coeff) in lc.as_ref() {
+            match var.get_unchecked() {
+                Index::Input(i) => {
+                    self.hasher.update([0u8]);
+                    self.hasher.update((i as u64).to_be_bytes());
+                }
+                Index::Aux(i) => {
+                    self.hasher.update([1u8]);
+                    self.hasher.update((i as u64).to_be_bytes());
+                }
+            }
+            let mut repr = Vec::new();
+            coeff.into_repr().write_be(&mut repr).unwrap();
+            self.hasher.update(&repr);
+        }
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for ShapeCs {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.aux += 1;
+        Ok(Variable::new_unchecked(Index::Aux(self.aux as usize - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inputs += 1;
+        Ok(Variable::new_unchecked(Index::Input(self.inputs as usize - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        self.constraints += 1;
+        self.absorb(&a(LinearCombination::zero()));
+        self.absorb(&b(LinearCombination::zero()));
+        self.absorb(&c(LinearCombination::zero()));
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self) {}
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+// shape of 'circuit', which may be blank
+pub fn shape<E: Engine, C: Circuit<E>>(circuit: C) -> Result<CircuitShape, SynthesisError> {
+    // the constant one is input 0
+    let mut cs = ShapeCs {
+        inputs: 1,
+        aux: 0,
+        constraints: 0,
+        hasher: Sha256::new(),
+    };
+    circuit.synthesize(&mut cs)?;
+    Ok(CircuitShape {
+        inputs: cs.inputs,
+        aux: cs.aux,
+        constraints: cs.constraints,
+        digest: cs.hasher.finalize().into(),
+    })
+}
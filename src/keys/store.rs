@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use once_cell::sync::OnceCell;
+use sapling_crypto::bellman::groth16::{self, Parameters, PreparedVerifyingKey, Proof};
+use sha2::{Digest, Sha256};
+
+use super::{shape, CircuitDescriptor, CircuitShape};
+use crate::circuit::{Engine, SynthesisError};
+
+// On-disk Groth16 key store.
+//
+// Keys live in '<dir>/<id>.params': a header with the descriptor id, the circuit shape and
+// the SHA-256 of the body, followed by the parameters in bellman's format. A file is loaded
+// the first time its circuit is asked for and kept in memory afterwards; a file whose shape
+// differs from the circuit built now is rejected rather than regenerated. Concurrent callers
+// asking for the same id wait for one load or generation instead of racing.
+
+const MAGIC: &[u8; 8] = b"VDFKEYS1";
+
+#[derive(Debug)]
+pub enum KeyError {
+    Io(io::Error),
+    Synthesis(SynthesisError),
+    // the body does not match its content hash, or the header is malformed
+    Corrupt,
+    // the file was generated for another circuit
+    ShapeMismatch,
+}
+
+impl From<io::Error> for KeyError {
+    fn from(e: io::Error) -> Self {
+        KeyError::Io(e)
+    }
+}
+
+impl From<SynthesisError> for KeyError {
+    fn from(e: SynthesisError) -> Self {
+        KeyError::Synthesis(e)
+    }
+}
+
+pub struct KeyStore<E: Engine> {
+    dir: PathBuf,
+    loaded: Mutex<HashMap<String, Arc<OnceCell<Arc<Parameters<E>>>>>>,
+}
+
+impl<E: Engine> KeyStore<E> {
+
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            loaded: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn path<D: CircuitDescriptor<E>>(&self, desc: &D) -> PathBuf {
+        self.dir.join(format!("{}.params", desc.id()))
+    }
+
+    // parameters for 'desc': from memory, else from disk, else freshly generated and saved
+    pub fn params<D: CircuitDescriptor<E>>(&self, desc: &D) -> Result<Arc<Parameters<E>>, KeyError> {
+        let id = desc.id();
+        // the map lock is only held to find the id's cell, the cell is held while loading
+        let cell = self.loaded.lock().unwrap().entry(id.clone()).or_default().clone();
+        let params = cell.get_or_try_init(|| {
+            let expected = shape(desc.blank())?;
+            let path = self.path(desc);
+            let params = if path.exists() {
+                read_params(&path, &id, &expected)?
+            } else {
+                let params = groth16::generate_random_parameters(desc.blank(), &mut rand04::thread_rng())?;
+                write_params(&path, &id, &expected, &params)?;
+                params
+            };
+            Ok::<_, KeyError>(Arc::new(params))
+        })?;
+        Ok(params.clone())
+    }
+
+    pub fn verifying_key<D: CircuitDescriptor<E>>(&self, desc: &D) -> Result<PreparedVerifyingKey<E>, KeyError> {
+        Ok(groth16::prepare_verifying_key(&self.params(desc)?.vk))
+    }
+
+    pub fn prove<D: CircuitDescriptor<E>>(&self, desc: &D, circuit: D::Circuit) -> Result<Proof<E>, KeyError> {
+        let params = self.params(desc)?;
+        Ok(groth16::create_random_proof(circuit, &*params, &mut rand04::thread_rng())?)
+    }
+}
+
+pub fn verify<E: Engine>(vk: &PreparedVerifyingKey<E>, proof: &Proof<E>, inputs: &[E::Fr]) -> bool {
+    groth16::verify_proof(vk, proof, inputs).unwrap_or(false)
+}
+
+fn write_params<E: Engine>(path: &Path, id: &str, shape: &CircuitShape, params: &Parameters<E>) -> Result<(), KeyError> {
+    let mut body = Vec::new();
+    params.write(&mut body)?;
+
+    let mut bytes = Vec::with_capacity(body.len() + 128);
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&(id.len() as u64).to_be_bytes());
+    bytes.extend_from_slice(id.as_bytes());
+    bytes.extend_from_slice(&shape.to_bytes());
+    bytes.extend_from_slice(&Sha256::digest(&body));
+    bytes.extend_from_slice(&body);
+
+    // write to a temporary file of its own then rename, so a crash never leaves a truncated
+    // file under the final name and concurrent writers do not share one
+    let dir = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(dir)?;
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    tmp.write_all(&bytes)?;
+    tmp.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+fn take<'a>(rest: &mut &'a [u8], n: usize) -> Result<&'a [u8], KeyError> {
+    if rest.len() < n {
+        return Err(KeyError::Corrupt);
+    }
+    let (head, tail) = rest.split_at(n);
+    *rest = tail;
+    Ok(head)
+}
+
+fn read_params<E: Engine>(path: &Path, id: &str, expected: &CircuitShape) -> Result<Parameters<E>, KeyError> {
+    let mut bytes = Vec::new();
+    fs::File::open(path)?.read_to_end(&mut bytes)?;
+
+    let mut rest = bytes.as_slice();
+    if take(&mut rest, MAGIC.len())? != MAGIC {
+        return Err(KeyError::Corrupt);
+    }
+    let id_len = u64::from_be_bytes(take(&mut rest, 8)?.try_into().unwrap()) as usize;
+    let file_id = take(&mut rest, id_len)?;
+    let file_shape = take(&mut rest, 56)?;
+    let hash = take(&mut rest, 32)?;
+    let body = rest;
+
+    if Sha256::digest(body).as_slice() != hash {
+        return Err(KeyError::Corrupt);
+    }
+    if file_id != id.as_bytes() || file_shape != expected.to_bytes().as_slice() {
+        return Err(KeyError::ShapeMismatch);
+    }
+    // the content hash only catches corruption, not a crafted file, so check the points
+    Ok(Parameters::read(body, true)?)
+}
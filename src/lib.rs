@@ -7,20 +7,97 @@ use bellman_bignat::group::{RsaQuotientGroup, SemiGroup, RsaGroup};
 use bellman_bignat::util::bench::Engine;
 use bellman_bignat::hash::{Hasher, circuit::CircuitHasher};
 use num_primes::{BigUint, Generator};
+use rand::RngCore;
+use rug::integer::Order;
 use rug::Integer;
 use rug::ops::Pow;
 
+pub mod aggregate;
+pub mod batch;
+pub mod beacon;
+pub mod circuit;
+pub mod commitment;
+pub mod continuous;
+pub mod homomorphic;
+pub mod keys;
+pub mod params;
+#[cfg(feature = "plonk")]
+pub mod plonk;
+pub mod poseidon;
+pub mod profile;
+pub mod protocol;
+pub mod simulator;
+pub mod solidity;
+pub mod threshold;
+pub mod timed_signature;
+pub mod timelock;
+pub mod wesolowski;
+
+use wesolowski::Proof;
+
 // From https://en.wikipedia.org/wiki/RSA_numbers#RSA-2048
 pub const RSA_2048: &str = "25195908475657893494027183240048398571429282126204032027777137836043662020707595556264018525880784406918290641249515082189298559149176184502808489120072844992687392807287776735971418347270261896375014971824691165077613379859095700097330459748808428401797429100642458691817195118746121515172654632282216869987549182422433637259085141865462043576798423387184774447920739934236584823824281198163815010674810451660377306056201619676256133844143603833904414952634432190114657544454178424020924616515723350778707749817125772467962926386356373289912154831438167899885040445364023527381951378636564391212010397122822120720357";
-const RSA_SIZE: usize = 2048;
-const ELEMENT_SIZE: usize = 5;
 const TIME_BASE: usize = 2;
 const TIME_ELEMENT_SIZE: u32 = 11;  //2^11 = 2048
 const TIME_MAX :usize = 30;
 
+#[derive(Clone, Debug)]
+pub enum DelayError {
+    // the delay exponent 2^(2^t) is only built for 0 <= t <= TIME_MAX
+    OutOfRange,
+    Parse(rug::integer::ParseIntegerError),
+}
+
+// time parameter 't' of an evaluation, turned into exponents by 'TrapdoorVDF::exponent'
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Delay {
+    time: Integer,
+}
+
+impl Delay {
+    pub fn new(time: u32) -> Result<Self, DelayError> {
+        if time as usize > TIME_MAX {
+            return Err(DelayError::OutOfRange);
+        }
+        Ok(Self {
+            time: Integer::from(time),
+        })
+    }
+
+    pub fn time(&self) -> &Integer {
+        &self.time
+    }
+
+    // the '&str' entry points of 'TrapdoorVDF' keep the baseline behaviour of clamping the
+    // time into 0..=TIME_MAX instead of rejecting it
+    fn clamped(time: &str) -> Self {
+        let time = Integer::from_str(time).unwrap().clamp(Integer::new(), Integer::from(TIME_MAX));
+        Self {
+            time: time,
+        }
+    }
+}
+
+impl FromStr for Delay {
+    type Err = DelayError;
+
+    fn from_str(time: &str) -> Result<Self, Self::Err> {
+        let time = Integer::from_str(time).map_err(DelayError::Parse)?;
+        Self::new(time.to_u32().ok_or(DelayError::OutOfRange)?)
+    }
+}
+
+// exponent reduced by the trapdoor: mod (p-1, q-1) when the factors are known, mod φ(N) otherwise
+pub(crate) enum Reduced {
+    Crt(Integer, Integer),
+    Totient(Integer),
+}
+
 pub struct TrapdoorVDF {
     pub group: RsaGroup,
     pub trapdoor: Integer,
+    // p and q, when the trapdoor holder generated the modulus
+    pub factors: Option<(Integer, Integer)>,
 }
 
 impl TrapdoorVDF {
@@ -33,6 +110,7 @@ impl TrapdoorVDF {
         Self {
             group: g,
             trapdoor: Integer::from(1usize),
+            factors: None,
         }
     }
 
@@ -55,10 +133,38 @@ impl TrapdoorVDF {
         Self{
             group: group,
             trapdoor: Integer::from_str(totient.to_string().as_str()).unwrap(),
+            factors: Some((
+                Integer::from_str(p.to_string().as_str()).unwrap(),
+                Integer::from_str(q.to_string().as_str()).unwrap(),
+            )),
         }
     }
 
-    fn rsa_exponent<'a, G, I> (g: &G, b: &G::Elem, l: &Integer, xs: I) -> G::Elem
+    // same as 'setup_with_random' with p and q drawn from 'rng', e.g. a seeded one
+    // for a reproducible simulation
+    pub fn setup_with_rng<R: RngCore>(g: &str, m_bits: usize, rng: &mut R) -> Self {
+        let bits = m_bits / 2;
+        let mut prime = || {
+            let mut bytes = vec![0u8; (bits + 7) / 8];
+            rng.fill_bytes(&mut bytes);
+            let mut n = Integer::from_digits(&bytes, Order::Msf).keep_bits(bits as u32);
+            n.set_bit(bits as u32 - 1, true);
+            n.next_prime()
+        };
+        let p = prime();
+        let q = prime();
+
+        let N = Integer::from(&p * &q);
+        let totient = Integer::from(&p - 1u32) * Integer::from(&q - 1u32);
+
+        Self{
+            group: RsaGroup::from_strs(g, N.to_string().as_str()),
+            trapdoor: totient,
+            factors: Some((p, q)),
+        }
+    }
+
+    pub(crate) fn rsa_exponent<'a, G, I> (g: &G, b: &G::Elem, l: &Integer, xs: I) -> G::Elem
     where
         G: SemiGroup,
         I: Iterator<Item=&'a Integer>
@@ -74,15 +180,68 @@ impl TrapdoorVDF {
         res
     }
 
-    fn calculate_exp(&self, base: Integer, time: Integer) -> Vec<Integer> {
-        let mut result = Vec::new();
-        let mut current_time = time.clone();
-        let base_two = 2;
+    // same as 'setup_with_random' with safe primes p = 2p'+1 and q = 2q'+1,
+    // as needed by the threshold trapdoor in 'threshold'
+    pub fn setup_with_safe_primes(g: &str, m_bits: &str) -> Self {
+        let modular_size = Integer::from_str(m_bits).unwrap().to_usize().unwrap();
+        let p = Integer::from_str(Generator::safe_prime(modular_size/2).to_string().as_str()).unwrap();
+        let q = Integer::from_str(Generator::safe_prime(modular_size/2).to_string().as_str()).unwrap();
+
+        let N = Integer::from(&p * &q);
+        let totient = Integer::from(&p - 1u32) * Integer::from(&q - 1u32);
+
+        Self{
+            group: RsaGroup::from_strs(g, N.to_string().as_str()),
+            trapdoor: totient,
+            factors: Some((p, q)),
+        }
+    }
 
-        //max value for time - otherwise, will be overflow for that
-        if current_time > TIME_MAX {
-            current_time = Integer::from(TIME_MAX);
+    // reduce 'floor(prod(xs)/l)' with the trapdoor
+    pub(crate) fn reduce(&self, l: &Integer, xs: &[Integer]) -> Reduced {
+        match &self.factors {
+            Some((p, q)) => Reduced::Crt(
+                wesolowski::quotient_mod(xs, l, &Integer::from(p - 1u32)),
+                wesolowski::quotient_mod(xs, l, &Integer::from(q - 1u32)),
+            ),
+            None => Reduced::Totient(wesolowski::quotient_mod(xs, l, &self.trapdoor)),
         }
+    }
+
+    // compute 'b^e % m' for an exponent reduced by the trapdoor.
+    // With p and q known, the two half-size exponentiations are done mod p and mod q,
+    // and the results are recombined with CRT.
+    pub(crate) fn power_reduced(&self, b: &Integer, e: &Reduced) -> Integer {
+        match (e, &self.factors) {
+            (Reduced::Crt(e_p, e_q), Some((p, q))) => {
+                let y_p = b.clone().pow_mod(e_p, p).unwrap();
+                let y_q = b.clone().pow_mod(e_q, q).unwrap();
+
+                // y = y_q + q * ((y_p - y_q) * q^-1 mod p)
+                let q_inv = q.clone().invert(p).unwrap();
+                let h = (Integer::from(&y_p - &y_q) * q_inv).rem_euc(p);
+                y_q + h * q
+            }
+            (Reduced::Totient(e), _) => self.group.power(b, e),
+            (Reduced::Crt(..), None) => unreachable!("CRT exponent without factors"),
+        }
+    }
+
+    // compute 'b^floor(prod(xs)/l) % m' with the exponent reduced by the trapdoor
+    pub(crate) fn trapdoor_exponent(&self, b: &Integer, l: &Integer, xs: &[Integer]) -> Integer {
+        self.power_reduced(b, &self.reduce(l, xs))
+    }
+
+    // exponents whose product is the delay exponent for 'delay'
+    pub fn exponent(&self, delay: &Delay) -> Vec<Integer> {
+        self.calculate_exp(Integer::from(TIME_BASE), delay)
+    }
+
+    // 'Delay' keeps t <= TIME_MAX, so the count of factors below fits in memory
+    fn calculate_exp(&self, base: Integer, delay: &Delay) -> Vec<Integer> {
+        let mut result = Vec::new();
+        let mut current_time = delay.time.clone();
+        let base_two = 2;
 
         if current_time > TIME_ELEMENT_SIZE {
             let exp = u32::pow(base_two, TIME_ELEMENT_SIZE);
@@ -97,7 +256,7 @@ impl TrapdoorVDF {
             ]
 
         } else {
-            let exp = u32::pow(base_two, Integer::to_u32(&time).unwrap());
+            let exp = u32::pow(base_two, Integer::to_u32(&delay.time).unwrap());
             let time_exp = self.group.power(&base, &Integer::from(exp));
             result.push(time_exp);
 
@@ -110,42 +269,31 @@ impl TrapdoorVDF {
 
         let time_base = Integer::from(TIME_BASE);
 
-        /*
-        // bits : 2^t (ex. t = 11 , 2^11 = 2048)
-        let time_exp = self.group.power(&time_base, &Integer::from_str(time).unwrap());
+        let delay = Delay::clamped(time);
+        let xs = self.calculate_exp(time_base, &delay);
 
-        // 2^bits 
-        // bits should be more than 2048 because this vdf is based on RSA_2048 with effective trapdoor
-        let mut vdf_exp = self.group.power(&self.group.generator(), &time_exp);
-        // let trapdoor_vdf_exp = vdf_exp.pow_mod(&Integer::from(1usize), &self.trapdoor).unwrap();
-        let mut trapdoor_vdf_exp = vdf_exp.pow_mod(&Integer::from(1usize), &self.max_time).unwrap();
-        let xs = vec![
-            trapdoor_vdf_exp.clone()
-        ];
-        */
-        let time_exp = Integer::from_str(time).unwrap();
-        let xs = self.calculate_exp(time_base, time_exp);
+        let b = Integer::from_str(base).unwrap();
 
-        let mut mod_xs = Integer::from(1usize);
-        for x in &xs {
-            mod_xs *= x;
-            mod_xs %= Integer::from_str(self.trapdoor.to_string().as_str()).unwrap();
-        }
-        let xs_totient = vec![
-            mod_xs.clone()
-        ];
+        self.trapdoor_exponent(&b, &Integer::from(1usize), &xs)
+    }
 
-        //println!("max_time[{}] in eval_trap : {:?}", &self.max_time.capacity(), &self.max_time);
-        
-        //println!("mod_xs[{}] in eval_trap : {:?}", mod_xs.capacity(),mod_xs);
-        //println!("trapdoor[{}] in eval_trap : {:?}",self.trapdoor.capacity(), self.trapdoor);
+    pub fn prove_with_trapdoor(&self, base: &str, time: &str) -> Proof {
+
+        let time_base = Integer::from(TIME_BASE);
 
-        //println!("diff : {:?}", self.trapdoor < mod_xs);
+        let delay = Delay::clamped(time);
+        let xs = self.calculate_exp(time_base, &delay);
 
         let b = Integer::from_str(base).unwrap();
 
-        //Self::rsa_exponent(&self.group, &b, &Integer::from(1usize), xs.iter())
-        Self::rsa_exponent(&self.group, &b, &Integer::from(1usize), xs_totient.iter())
+        let y = self.trapdoor_exponent(&b, &Integer::from(1usize), &xs);
+        let l = wesolowski::challenge(&self.group, &b, &y);
+        let pi = self.trapdoor_exponent(&b, &l, &xs);
+
+        Proof {
+            output: y,
+            pi: pi,
+        }
     }
 
     pub fn eval(&self, base: &str, time: &str) -> Integer {
@@ -164,8 +312,8 @@ impl TrapdoorVDF {
             eval_vdf_exp
         ];
         */
-        let time_exp = Integer::from_str(time).unwrap();
-        let xs = self.calculate_exp(time_base, time_exp);
+        let delay = Delay::clamped(time);
+        let xs = self.calculate_exp(time_base, &delay);
         //println!("xs'0 size: {}", xs[0].capacity());
 
         let b = Integer::from_str(base).unwrap();
@@ -174,6 +322,80 @@ impl TrapdoorVDF {
 
     }
 
+    pub fn prove(&self, base: &str, time: &str) -> Proof {
+
+        let time_base = Integer::from(TIME_BASE);
+
+        let delay = Delay::clamped(time);
+        let xs = self.calculate_exp(time_base, &delay);
+
+        let b = Integer::from_str(base).unwrap();
+
+        let y = Self::rsa_exponent(&self.group, &b, &Integer::from(1usize), xs.iter());
+        let l = wesolowski::challenge(&self.group, &b, &y);
+        let pi = Self::rsa_exponent(&self.group, &b, &l, xs.iter());
+
+        Proof {
+            output: y,
+            pi: pi,
+        }
+    }
+
+    pub fn verify(&self, base: &str, time: &str, proof: &Proof) -> bool {
+
+        let time_base = Integer::from(TIME_BASE);
+
+        let delay = Delay::clamped(time);
+        let xs = self.calculate_exp(time_base, &delay);
+
+        let b = Integer::from_str(base).unwrap();
+
+        wesolowski::verify(&self.group, &b, &xs, proof)
+    }
+
+    // true when p and q or φ(N) are known, as after 'setup_with_random'
+    pub fn has_trapdoor(&self) -> bool {
+        self.factors.is_some() || self.trapdoor != 1
+    }
+
+    // 'eval' .. 'verify' on parsed values
+
+    pub fn eval_delay(&self, base: &Integer, delay: &Delay) -> Integer {
+        Self::rsa_exponent(&self.group, base, &Integer::from(1usize), self.exponent(delay).iter())
+    }
+
+    pub fn eval_delay_with_trapdoor(&self, base: &Integer, delay: &Delay) -> Integer {
+        self.trapdoor_exponent(base, &Integer::from(1usize), &self.exponent(delay))
+    }
+
+    pub fn prove_delay(&self, base: &Integer, delay: &Delay) -> Proof {
+        let xs = self.exponent(delay);
+        let y = Self::rsa_exponent(&self.group, base, &Integer::from(1usize), xs.iter());
+        let l = wesolowski::challenge(&self.group, base, &y);
+        let pi = Self::rsa_exponent(&self.group, base, &l, xs.iter());
+
+        Proof {
+            output: y,
+            pi: pi,
+        }
+    }
+
+    pub fn prove_delay_with_trapdoor(&self, base: &Integer, delay: &Delay) -> Proof {
+        let xs = self.exponent(delay);
+        let y = self.trapdoor_exponent(base, &Integer::from(1usize), &xs);
+        let l = wesolowski::challenge(&self.group, base, &y);
+        let pi = self.trapdoor_exponent(base, &l, &xs);
+
+        Proof {
+            output: y,
+            pi: pi,
+        }
+    }
+
+    pub fn verify_delay(&self, base: &Integer, delay: &Delay, proof: &Proof) -> bool {
+        wesolowski::verify(&self.group, base, &self.exponent(delay), proof)
+    }
+
 }
 
 
@@ -184,7 +406,7 @@ mod tests {
     use bellman_bignat::group::{RsaGroup, SemiGroup};
     use rug::Integer;
 
-    use crate::{RSA_2048};
+    use crate::{RSA_2048, TrapdoorVDF};
 
     use num_primes::{Generator, BigUint};
 
@@ -315,4 +537,21 @@ mod tests {
         let res_mod = rsa_exponent(&g, &b, &l, xs_totient.iter());
         println!("res_mod : {}", res_mod);
     }
+
+    #[test]
+    fn test_crt_trapdoor() {
+        let vdf = TrapdoorVDF::setup_with_random("2", "512");
+
+        let res_eval = vdf.eval("5", "12");
+        let res_crt = vdf.eval_with_trapdoor("5", "12");
+        assert_eq!(res_crt, res_eval);
+
+        let mut vdf_phi = TrapdoorVDF::setup_with_random("2", "512");
+        vdf_phi.factors = None;
+        assert_eq!(vdf_phi.eval_with_trapdoor("5", "12"), vdf_phi.eval("5", "12"));
+
+        let proof = vdf.prove_with_trapdoor("5", "12");
+        assert_eq!(proof, vdf.prove("5", "12"));
+        assert!(vdf.verify("5", "12", &proof));
+    }
 }
@@ -0,0 +1,94 @@
+use rug::Integer;
+use sapling_crypto::bellman::pairing::ff::PrimeField;
+
+use crate::wesolowski::CHALLENGE_BITS;
+
+// Limb layout of numbers mod N in circuits. The circuits allocate and hash numbers with it,
+// and the native code that builds their witnesses checks values against it first, so that
+// nothing is silently truncated to the limbs.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParamsError {
+    // limbs of zero bits
+    EmptyLimbs,
+    // a column of limb products does not fit the circuit field
+    LimbTooWide,
+    // the limbs cannot hold 'modulus_bits'
+    TooFewLimbs,
+    ModulusTooLarge,
+    // not an element of Z_m
+    ValueOutOfRange,
+    // the challenge prime must be shorter than the modulus
+    ChallengeTooLarge,
+    // hashing to a prime of 'challenge_bits' bits gave up, e.g. with too few bits
+    NoChallengePrime,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CircuitParams {
+    pub limb_width: usize,
+    pub n_limbs: usize,
+    pub modulus_bits: usize,
+    // bits of entropy of the Fiat-Shamir challenge prime
+    pub challenge_bits: usize,
+}
+
+impl Default for CircuitParams {
+    // RSA-2048 in 32-bit limbs
+    fn default() -> Self {
+        Self::new(32, 2048, CHALLENGE_BITS as usize).unwrap()
+    }
+}
+
+impl CircuitParams {
+
+    // the fewest limbs of 'limb_width' bits that hold 'modulus_bits'
+    pub fn new(limb_width: usize, modulus_bits: usize, challenge_bits: usize) -> Result<Self, ParamsError> {
+        if limb_width == 0 {
+            return Err(ParamsError::EmptyLimbs);
+        }
+        Ok(Self {
+            limb_width: limb_width,
+            n_limbs: (modulus_bits + limb_width - 1) / limb_width,
+            modulus_bits: modulus_bits,
+            challenge_bits: challenge_bits,
+        })
+    }
+
+    // bits the limbs can hold
+    pub fn capacity(&self) -> usize {
+        self.limb_width * self.n_limbs
+    }
+
+    // check the layout against the scalar field F of the circuit
+    pub fn check<F: PrimeField>(&self) -> Result<(), ParamsError> {
+        if self.capacity() < self.modulus_bits {
+            return Err(ParamsError::TooFewLimbs);
+        }
+        // n_limbs products below 2^(2 limb_width) add up in one column
+        let column_bits = 2 * self.limb_width + (usize::BITS - self.n_limbs.leading_zeros()) as usize;
+        if self.limb_width == 0 || column_bits >= F::CAPACITY as usize {
+            return Err(ParamsError::LimbTooWide);
+        }
+        if self.challenge_bits == 0 || self.challenge_bits >= self.modulus_bits {
+            return Err(ParamsError::ChallengeTooLarge);
+        }
+        Ok(())
+    }
+
+    pub fn check_modulus(&self, m: &Integer) -> Result<(), ParamsError> {
+        if m.significant_bits() as usize > self.modulus_bits {
+            return Err(ParamsError::ModulusTooLarge);
+        }
+        Ok(())
+    }
+
+    // 'x' is in Z_m and 'm' fits the layout
+    pub fn check_element(&self, m: &Integer, x: &Integer) -> Result<(), ParamsError> {
+        self.check_modulus(m)?;
+        if *x < 0 || x >= m {
+            return Err(ParamsError::ValueOutOfRange);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,456 @@
+use dusk_plonk::prelude::*;
+use dusk_poseidon::sponge;
+use rand::rngs::OsRng;
+use rug::Integer;
+
+use crate::params::{CircuitParams, ParamsError};
+use crate::wesolowski::{self, CHALLENGE_BITS};
+use crate::{Delay, TrapdoorVDF};
+
+// PLONK versions of the VDF circuits (dusk-plonk over BLS12-381), for users who cannot run
+// a per-circuit trusted setup: one universal setup serves every circuit up to its size.
+//
+// Numbers mod N are limbs in the layout of a 'CircuitParams'. 'a * b = q * N + r' is checked
+// column by column over the limb products, with signed carries between columns that must
+// end at zero, and 'r < N' is checked by 'r + d + 1 = N' for some range checked d, so every
+// product is reduced. Carries are computed in i128, which bounds the limb width.
+
+const TRANSCRIPT: &[u8] = b"vdf_snark/plonk";
+
+// bits of the Wesolowski exponents l and r: 'next_prime' may carry l past CHALLENGE_BITS
+const EXPONENT_BITS: usize = CHALLENGE_BITS as usize + 1;
+
+// the range gadget takes even widths, and a column of limb products must fit an i128
+pub fn check_params(params: &CircuitParams) -> Result<(), ParamsError> {
+    let column_bits = 2 * params.limb_width + (usize::BITS - params.n_limbs.leading_zeros()) as usize + 2;
+    if params.limb_width == 0 || params.limb_width % 2 != 0 || params.limb_width > 62 || column_bits >= 127 {
+        return Err(ParamsError::LimbTooWide);
+    }
+    if params.capacity() < params.modulus_bits {
+        return Err(ParamsError::TooFewLimbs);
+    }
+    Ok(())
+}
+
+fn limbs(n: &Integer, params: &CircuitParams) -> Vec<u64> {
+    let width = params.limb_width as u32;
+    (0..params.n_limbs as u32)
+        .map(|i| Integer::from(n >> (i * width)).keep_bits(width).to_u64().unwrap())
+        .collect()
+}
+
+fn scalar(v: i128) -> BlsScalar {
+    if v < 0 {
+        -BlsScalar::from(v.unsigned_abs() as u64)
+    } else {
+        BlsScalar::from(v as u64)
+    }
+}
+
+// a nonnegative number below 2^192
+fn big_scalar(v: &Integer) -> BlsScalar {
+    let word = |i: u32| Integer::from(v >> (64 * i)).keep_bits(64).to_u64().unwrap();
+    BlsScalar::from_raw([word(0), word(1), word(2), 0])
+}
+
+// the bits of a public exponent, most significant first
+struct Bits {
+    bits: Vec<Witness>,
+    values: Vec<bool>,
+}
+
+impl Bits {
+    // 'n' boolean witnesses that recompose to the public input 'e'
+    fn publish(composer: &mut TurboComposer, e: &Integer, n: usize) -> Self {
+        let values: Vec<bool> = (0..n as u32).rev().map(|i| e.get_bit(i)).collect();
+        let mut acc = TurboComposer::constant_zero();
+        let bits = values
+            .iter()
+            .map(|v| {
+                let bit = composer.append_witness(BlsScalar::from(*v as u64));
+                composer.component_boolean(bit);
+                acc = composer.gate_add(Constraint::new().left(BlsScalar::from(2u64)).right(BlsScalar::one()).a(acc).b(bit));
+                bit
+            })
+            .collect();
+        composer.assert_equal_constant(acc, BlsScalar::zero(), Some(-big_scalar(e)));
+        Self {
+            bits: bits,
+            values: values,
+        }
+    }
+}
+
+// a number with its range checked limbs
+struct Nat {
+    params: CircuitParams,
+    limbs: Vec<Witness>,
+    values: Vec<u64>,
+}
+
+impl Nat {
+    fn alloc(composer: &mut TurboComposer, value: &Integer, params: &CircuitParams) -> Self {
+        let values = limbs(value, params);
+        let limbs = values
+            .iter()
+            .map(|v| {
+                let w = composer.append_witness(BlsScalar::from(*v));
+                composer.component_range(w, params.limb_width);
+                w
+            })
+            .collect();
+        Self {
+            params: *params,
+            limbs: limbs,
+            values: values,
+        }
+    }
+
+    // 'value' with every limb fixed by the circuit
+    fn constant(composer: &mut TurboComposer, value: &Integer, params: &CircuitParams) -> Self {
+        let nat = Self::alloc(composer, value, params);
+        for (w, v) in nat.limbs.iter().zip(&nat.values) {
+            composer.assert_equal_constant(*w, BlsScalar::from(*v), None);
+        }
+        nat
+    }
+
+    fn value(&self) -> Integer {
+        self.values
+            .iter()
+            .rev()
+            .fold(Integer::new(), |acc, v| (acc << self.params.limb_width as u32) + *v)
+    }
+
+    // make every limb a public input
+    fn publish(&self, composer: &mut TurboComposer) {
+        for (w, v) in self.limbs.iter().zip(&self.values) {
+            composer.assert_equal_constant(*w, BlsScalar::zero(), Some(-BlsScalar::from(*v)));
+        }
+    }
+
+    fn equal(&self, composer: &mut TurboComposer, other: &Nat) {
+        for (a, b) in self.limbs.iter().zip(&other.limbs) {
+            composer.assert_equal(*a, *b);
+        }
+    }
+
+    // 'self' if 'bit' is set, else 'other'
+    fn select(&self, composer: &mut TurboComposer, bit: Witness, bit_value: bool, other: &Nat) -> Nat {
+        let limbs = self
+            .limbs
+            .iter()
+            .zip(&other.limbs)
+            .map(|(a, b)| composer.component_select(bit, *a, *b))
+            .collect();
+        Nat {
+            params: self.params,
+            limbs: limbs,
+            values: if bit_value { self.values.clone() } else { other.values.clone() },
+        }
+    }
+
+    // 'self < m', by 'self + d + 1 = m' for d in range
+    fn assert_less(&self, composer: &mut TurboComposer, m: &Nat) {
+        let m_value = m.value();
+        // blank circuits have m = 0
+        let d = if m_value == 0 {
+            Integer::new()
+        } else {
+            m_value - self.value() - 1u32
+        };
+        let d = Nat::alloc(composer, &d, &self.params);
+        self.assert_sum(composer, &d, true, m);
+    }
+
+    // 'self + d + [one] = m'
+    fn assert_sum(&self, composer: &mut TurboComposer, d: &Nat, one: bool, m: &Nat) {
+        let width = self.params.limb_width;
+        let mut carry = TurboComposer::constant_zero();
+        let mut carry_value = 0i128;
+        for k in 0..self.limbs.len() {
+            // a_k + d_k + [one and k = 0] + carry_in = m_k + carry_out * 2^w, with a boolean carry
+            let first = one && k == 0;
+            let one = if first { BlsScalar::one() } else { BlsScalar::zero() };
+            let sum = composer.gate_add(
+                Constraint::new()
+                    .left(BlsScalar::one())
+                    .right(BlsScalar::one())
+                    .fourth(BlsScalar::one())
+                    .constant(one)
+                    .a(self.limbs[k])
+                    .b(d.limbs[k])
+                    .d(carry),
+            );
+            let sum_value = self.values[k] as i128 + d.values[k] as i128 + first as i128 + carry_value;
+            let next_value = (sum_value - m.values[k] as i128) >> width;
+            let next = composer.append_witness(scalar(next_value));
+            composer.component_boolean(next);
+            composer.append_gate(
+                Constraint::new()
+                    .left(BlsScalar::one())
+                    .right(-BlsScalar::one())
+                    .fourth(-BlsScalar::from(1u64 << width))
+                    .a(sum)
+                    .b(m.limbs[k])
+                    .d(next),
+            );
+
+            carry = next;
+            carry_value = next_value;
+        }
+        composer.assert_equal_constant(carry, BlsScalar::zero(), None);
+    }
+
+    // 'self * other % m'
+    fn mult_mod(&self, composer: &mut TurboComposer, other: &Nat, m: &Nat) -> Nat {
+        let n = self.limbs.len();
+        let width = self.params.limb_width;
+        let m_value = m.value();
+        // blank circuits have m = 0
+        let (q, r) = if m_value == 0 {
+            (Integer::new(), Integer::new())
+        } else {
+            Integer::from(self.value() * other.value()).div_rem_euc(m_value)
+        };
+        let q = Nat::alloc(composer, &q, &self.params);
+        let r = Nat::alloc(composer, &r, &self.params);
+
+        // |carry| < 2^(carry_bits - 1), rounded up to the even sizes the range gadget takes
+        let carry_bits = width + (usize::BITS - n.leading_zeros()) as usize + 2;
+        let carry_bits = carry_bits + carry_bits % 2;
+        let offset = 1i128 << (carry_bits - 1);
+
+        let mut carry = TurboComposer::constant_zero();
+        let mut carry_value = 0i128;
+        for k in 0..2 * n - 1 {
+            // t_k = sum a_i b_j - sum q_i m_j - r_k over i + j = k
+            let mut acc = TurboComposer::constant_zero();
+            let mut acc_value = 0i128;
+            for i in k.saturating_sub(n - 1)..=k.min(n - 1) {
+                let j = k - i;
+                acc = composer.gate_mul(Constraint::new().mult(BlsScalar::one()).fourth(BlsScalar::one()).a(self.limbs[i]).b(other.limbs[j]).d(acc));
+                acc = composer.gate_mul(Constraint::new().mult(-BlsScalar::one()).fourth(BlsScalar::one()).a(q.limbs[i]).b(m.limbs[j]).d(acc));
+                acc_value += self.values[i] as i128 * other.values[j] as i128 - q.values[i] as i128 * m.values[j] as i128;
+            }
+            if k < n {
+                acc = composer.gate_add(Constraint::new().left(-BlsScalar::one()).fourth(BlsScalar::one()).a(r.limbs[k]).d(acc));
+                acc_value -= r.values[k] as i128;
+            }
+
+            // t_k + carry_in = carry_out * 2^w
+            let next_value = (acc_value + carry_value) >> width;
+            let next = composer.append_witness(scalar(next_value));
+            composer.append_gate(
+                Constraint::new()
+                    .left(BlsScalar::one())
+                    .right(BlsScalar::one())
+                    .fourth(-BlsScalar::from(1u64 << width))
+                    .a(acc)
+                    .b(carry)
+                    .d(next),
+            );
+            let shifted = composer.gate_add(Constraint::new().left(BlsScalar::one()).constant(scalar(offset)).a(next));
+            composer.component_range(shifted, carry_bits);
+
+            carry = next;
+            carry_value = next_value;
+        }
+        composer.assert_equal_constant(carry, BlsScalar::zero(), None);
+        r.assert_less(composer, m);
+        r
+    }
+}
+
+fn padded_gates<C: Circuit>(circuit: &mut C) -> usize {
+    let mut composer = TurboComposer::new();
+    circuit.gadget(&mut composer).unwrap();
+    composer.gates().next_power_of_two()
+}
+
+fn public_limbs(n: &Integer, params: &CircuitParams) -> Vec<PublicInputValue> {
+    limbs(n, params).into_iter().map(|v| BlsScalar::from(v).into()).collect()
+}
+
+// Poseidon commitment to a VDF output, over its limbs
+pub fn commit(y: &Integer, params: &CircuitParams) -> BlsScalar {
+    let limbs: Vec<BlsScalar> = limbs(y, params).into_iter().map(BlsScalar::from).collect();
+    sponge::hash(&limbs)
+}
+
+// Knowledge of y with 'commitment = commit(y)'. Public input: the commitment.
+#[derive(Debug, Default)]
+pub struct CommitmentCircuit {
+    pub params: CircuitParams,
+    pub y: Integer,
+    pub commitment: BlsScalar,
+    padded: usize,
+}
+
+impl CommitmentCircuit {
+    pub fn new(params: CircuitParams, y: &Integer) -> Result<Self, ParamsError> {
+        check_params(&params)?;
+        if *y < 0 || y.significant_bits() as usize > params.capacity() {
+            return Err(ParamsError::ValueOutOfRange);
+        }
+        let mut circuit = Self {
+            params: params,
+            y: y.clone(),
+            commitment: commit(y, &params),
+            padded: 0,
+        };
+        circuit.padded = padded_gates(&mut circuit);
+        Ok(circuit)
+    }
+
+    // circuit shape only, for compiling keys
+    pub fn blank(params: CircuitParams) -> Result<Self, ParamsError> {
+        Self::new(params, &Integer::new())
+    }
+}
+
+impl Circuit for CommitmentCircuit {
+    const CIRCUIT_ID: [u8; 32] = [0x01; 32];
+
+    fn gadget(&mut self, composer: &mut TurboComposer) -> Result<(), Error> {
+        let y = Nat::alloc(composer, &self.y, &self.params);
+        let hash = sponge::gadget(composer, &y.limbs);
+        composer.assert_equal_constant(hash, BlsScalar::zero(), Some(-self.commitment));
+        Ok(())
+    }
+
+    fn public_inputs(&self) -> Vec<PublicInputValue> {
+        vec![self.commitment.into()]
+    }
+
+    fn padded_gates(&self) -> usize {
+        self.padded
+    }
+}
+
+// Wesolowski verification in the quotient group by {1, -1}, as 'wesolowski::verify_quotient':
+// y is canonical, 'y <= (m - 1) / 2', and 'pi^l * x^r = ±y (mod m)'. Without the sign, y and
+// 'm - y' would be two outputs for one x. Proofs are made by 'TrapdoorVDF::prove_batch_quotient'
+// or 'CheckpointProver::prove_quotient', whose challenge is the native
+// 'l = wesolowski::challenge(m, x, y)' with 'r = prod(xs) % l' for the delay exponent. A
+// verifier recomputes l and r from x and y outside the circuit, so both are public inputs,
+// checked bit by bit; one square-and-multiply chain over the bits of l and r computes both
+// powers. x must be reduced mod m. Public inputs: the limbs of m, x and y, then l and r.
+#[derive(Debug, Default)]
+pub struct WesolowskiCircuit {
+    pub params: CircuitParams,
+    pub modulus: Integer,
+    pub x: Integer,
+    pub y: Integer,
+    pub pi: Integer,
+    pub l: Integer,
+    pub r: Integer,
+    padded: usize,
+}
+
+impl WesolowskiCircuit {
+    // circuit for 'proof' of 'delay' on x; the proof itself is only checked by the circuit
+    pub fn new(params: CircuitParams, vdf: &TrapdoorVDF, delay: &Delay, x: &Integer, proof: &wesolowski::Proof) -> Result<Self, ParamsError> {
+        check_params(&params)?;
+        let m = &vdf.group.m;
+        params.check_element(m, x)?;
+        params.check_element(m, &proof.output)?;
+        params.check_element(m, &proof.pi)?;
+        if wesolowski::canonical(&vdf.group, &proof.output) != proof.output {
+            return Err(ParamsError::ValueOutOfRange);
+        }
+        let l = wesolowski::challenge(&vdf.group, x, &proof.output);
+        let r = wesolowski::exponent_mod(&vdf.exponent(delay), &l);
+        let mut circuit = Self {
+            params: params,
+            modulus: m.clone(),
+            x: x.clone(),
+            y: proof.output.clone(),
+            pi: proof.pi.clone(),
+            l: l,
+            r: r,
+            padded: 0,
+        };
+        circuit.padded = padded_gates(&mut circuit);
+        Ok(circuit)
+    }
+
+    // circuit shape only, for compiling keys
+    pub fn blank(params: CircuitParams) -> Result<Self, ParamsError> {
+        check_params(&params)?;
+        let mut circuit = Self {
+            params: params,
+            ..Self::default()
+        };
+        circuit.padded = padded_gates(&mut circuit);
+        Ok(circuit)
+    }
+}
+
+impl Circuit for WesolowskiCircuit {
+    const CIRCUIT_ID: [u8; 32] = [0x02; 32];
+
+    fn gadget(&mut self, composer: &mut TurboComposer) -> Result<(), Error> {
+        let m = Nat::alloc(composer, &self.modulus, &self.params);
+        m.publish(composer);
+        let x = Nat::alloc(composer, &self.x, &self.params);
+        x.publish(composer);
+        let y = Nat::alloc(composer, &self.y, &self.params);
+        y.publish(composer);
+        let l = Bits::publish(composer, &self.l, EXPONENT_BITS);
+        let r = Bits::publish(composer, &self.r, EXPONENT_BITS);
+        x.assert_less(composer, &m);
+        // y + neg = m with y < neg, so y <= (m - 1) / 2 for odd m and neg = m - y
+        let neg = Nat::alloc(composer, &Integer::from(&self.modulus - &self.y), &self.params);
+        y.assert_sum(composer, &neg, false, &m);
+        y.assert_less(composer, &neg);
+        let pi = Nat::alloc(composer, &self.pi, &self.params);
+
+        let mut acc = Nat::constant(composer, &Integer::from(1u32), &self.params);
+        for i in 0..EXPONENT_BITS {
+            acc = acc.mult_mod(composer, &acc, &m);
+            let with_pi = acc.mult_mod(composer, &pi, &m);
+            acc = with_pi.select(composer, l.bits[i], l.values[i], &acc);
+            let with_x = acc.mult_mod(composer, &x, &m);
+            acc = with_x.select(composer, r.bits[i], r.values[i], &acc);
+        }
+        // acc = y or acc = m - y
+        let negated = acc.value() != self.y;
+        let sign = composer.append_witness(BlsScalar::from(negated as u64));
+        composer.component_boolean(sign);
+        let target = neg.select(composer, sign, negated, &y);
+        acc.equal(composer, &target);
+        Ok(())
+    }
+
+    fn public_inputs(&self) -> Vec<PublicInputValue> {
+        let mut inputs = public_limbs(&self.modulus, &self.params);
+        inputs.extend(public_limbs(&self.x, &self.params));
+        inputs.extend(public_limbs(&self.y, &self.params));
+        inputs.push(big_scalar(&self.l).into());
+        inputs.push(big_scalar(&self.r).into());
+        inputs
+    }
+
+    fn padded_gates(&self) -> usize {
+        self.padded
+    }
+}
+
+// universal parameters for every circuit of up to 'max_gates' gates
+pub fn universal_setup(max_gates: usize) -> Result<PublicParameters, Error> {
+    PublicParameters::setup(2 * max_gates.next_power_of_two(), &mut OsRng)
+}
+
+// keys of the circuit shape of 'blank', derived from the universal parameters
+pub fn compile<C: Circuit>(pp: &PublicParameters, blank: &mut C) -> Result<(ProverKey, VerifierData), Error> {
+    blank.compile(pp)
+}
+
+pub fn prove<C: Circuit>(pp: &PublicParameters, pk: &ProverKey, circuit: &mut C) -> Result<Proof, Error> {
+    circuit.prove(pp, pk, TRANSCRIPT, &mut OsRng)
+}
+
+pub fn verify<C: Circuit>(pp: &PublicParameters, vd: &VerifierData, proof: &Proof, inputs: &[PublicInputValue]) -> bool {
+    C::verify(pp, vd, proof, inputs, TRANSCRIPT).is_ok()
+}
@@ -0,0 +1,316 @@
+use ark_ff::{BigInteger, Field, FpParameters, One, PrimeField, Zero};
+
+// Poseidon parameters for any prime field, following the reference scripts of Grassi et al.
+// ('generate_parameters_grain.sage' and 'calc_round_numbers.py').
+//
+// Round constants and the MDS matrix come from the Grain LFSR seeded with the field, width
+// and round numbers, so the same configuration always gives the same tables. Constants are
+// sampled by rejection; the MDS matrix is the Cauchy matrix '1 / (x_i + y_j)' over 2t
+// distinct elements reduced mod p, resampled until it passes the reference's algorithms 1-3
+// against infinitely long subspace trails through the partial rounds.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoseidonError {
+    // the S-box 'x^alpha' is a permutation only when gcd(alpha, p - 1) = 1
+    AlphaNotPermutation,
+    WidthTooSmall,
+    // no round numbers up to the search bounds reach the security level
+    NoSecureRounds,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PoseidonParams<F: PrimeField> {
+    // state width t
+    pub width: usize,
+    pub alpha: u64,
+    pub full_rounds: usize,
+    pub partial_rounds: usize,
+    // t constants per round
+    pub ark: Vec<Vec<F>>,
+    pub mds: Vec<Vec<F>>,
+}
+
+// self-shrinking Grain LFSR over 80 bits
+struct Grain {
+    state: [bool; 80],
+    pos: usize,
+}
+
+impl Grain {
+
+    // field type (prime), S-box (x^alpha), field size, t, R_F and R_P, then 30 ones
+    fn new(field_bits: usize, width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        let mut init = Vec::with_capacity(80);
+        for (v, bits) in [(1, 2), (0, 4), (field_bits, 12), (width, 12), (full_rounds, 10), (partial_rounds, 10)] {
+            init.extend((0..bits).rev().map(|i| (v >> i) & 1 == 1));
+        }
+        init.extend([true; 30]);
+
+        let mut grain = Self {
+            state: init.try_into().unwrap(),
+            pos: 0,
+        };
+        for _ in 0..160 {
+            grain.step();
+        }
+        grain
+    }
+
+    fn step(&mut self) -> bool {
+        let b = |i: usize| self.state[(self.pos + i) % 80];
+        let new = b(62) ^ b(51) ^ b(38) ^ b(23) ^ b(13) ^ b(0);
+        self.state[self.pos] = new;
+        self.pos = (self.pos + 1) % 80;
+        new
+    }
+
+    // output the second bit of each pair whose first bit is set
+    fn bit(&mut self) -> bool {
+        while !self.step() {
+            self.step();
+        }
+        self.step()
+    }
+
+    fn bits(&mut self, n: usize) -> Vec<bool> {
+        (0..n).map(|_| self.bit()).collect()
+    }
+
+    // n bits as an element below p, resampled until they are
+    fn field_element<F: PrimeField>(&mut self, n: usize) -> F {
+        loop {
+            if let Some(f) = F::from_repr(F::BigInt::from_bits_be(&self.bits(n))) {
+                return f;
+            }
+        }
+    }
+
+    // n bits reduced mod p
+    fn field_element_mod<F: PrimeField>(&mut self, n: usize) -> F {
+        let mut bits = vec![false; (8 - n % 8) % 8];
+        bits.extend(self.bits(n));
+        let bytes: Vec<u8> = bits
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0u8, |acc, b| (acc << 1) | *b as u8))
+            .collect();
+        F::from_be_bytes_mod_order(&bytes)
+    }
+}
+
+fn field_bits<F: PrimeField>() -> usize {
+    F::Params::MODULUS_BITS as usize
+}
+
+fn log2_modulus<F: PrimeField>() -> f64 {
+    let modulus = F::Params::MODULUS;
+    let words = modulus.as_ref();
+    let k = words.iter().rposition(|w| *w != 0).unwrap();
+    if k == 0 {
+        return (words[0] as f64).log2();
+    }
+    (words[k] as f64 * 2f64.powi(64) + words[k - 1] as f64).log2() + 64.0 * (k - 1) as f64
+}
+
+fn modulus_mod<F: PrimeField>(d: u64) -> u64 {
+    let modulus = F::Params::MODULUS;
+    modulus
+        .as_ref()
+        .iter()
+        .rev()
+        .fold(0u128, |acc, w| ((acc << 64) | *w as u128) % d as u128) as u64
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn check_alpha<F: PrimeField>(alpha: u64) -> Result<(), PoseidonError> {
+    // p - 1 mod alpha
+    let r = (modulus_mod::<F>(alpha) + alpha - 1) % alpha;
+    if alpha < 3 || gcd(alpha, r) != 1 {
+        return Err(PoseidonError::AlphaNotPermutation);
+    }
+    Ok(())
+}
+
+// log2 of the binomial coefficient C(n, k)
+fn log2_binomial(n: f64, k: f64) -> f64 {
+    (0..k as u64).map(|i| ((n - i as f64) / (i as f64 + 1.0)).log2()).sum()
+}
+
+// the statistical, interpolation and Groebner basis bounds on R_F for 'partial_rounds'
+fn is_secure<F: PrimeField>(width: usize, alpha: u64, security_bits: usize, full_rounds: usize, partial_rounds: usize) -> bool {
+    let (t, a, m) = (width as f64, alpha as f64, security_bits as f64);
+    let (rf, rp) = (full_rounds as f64, partial_rounds as f64);
+    let log2_p = log2_modulus::<F>();
+    let log_a = |x: f64| x.ln() / a.ln();
+
+    let rf_1 = if m <= (log2_p - (a - 1.0) / 2.0).floor() * (t + 1.0) { 6.0 } else { 10.0 };
+    let rf_2 = 1.0 + (log_a(2.0) * m.min(field_bits::<F>() as f64)).ceil() + log_a(t).ceil() - rp;
+    let rf_3 = log_a(2.0) * m.min(log2_p) - rp;
+    let rf_4 = t - 1.0 + log_a(2.0) * (m / (t + 1.0)).min(log2_p / 2.0) - rp;
+    let rf_5 = (t - 2.0 + m / (2.0 * a.log2()) - rp) / (t - 1.0);
+    let rf_max = [rf_1, rf_2, rf_3, rf_4, rf_5].iter().map(|r| r.ceil()).fold(f64::MIN, f64::max);
+
+    // the attack of https://eprint.iacr.org/2023/537
+    let r = (t / 3.0).floor();
+    let over = (rf - 1.0) * t + rp + r + r * (rf / 2.0) + rp + a;
+    let under = r * (rf / 2.0) + rp + a;
+    let cost_gb4 = (2.0 * log2_binomial(over, under)).ceil();
+
+    rf >= rf_max && cost_gb4 >= m
+}
+
+// (R_F, R_P) with the fewest S-boxes 't * R_F + R_P' for 'security_bits', including the
+// reference's margin of two full rounds and 7.5% more partial rounds, with R_P rounded up to
+// a multiple of t as in the published tables
+pub fn round_numbers<F: PrimeField>(width: usize, alpha: u64, security_bits: usize) -> Result<(usize, usize), PoseidonError> {
+    if width < 2 {
+        return Err(PoseidonError::WidthTooSmall);
+    }
+    check_alpha::<F>(alpha)?;
+
+    let mut best: Option<(usize, usize, usize)> = None;
+    for rp in 1..500 {
+        // more full rounds for the same R_P only cost more
+        if let Some(rf) = (4..100).step_by(2).find(|rf| is_secure::<F>(width, alpha, security_bits, *rf, rp)) {
+            let rp = (rp as f64 * 1.075).ceil() as usize;
+            let (rf, rp) = (rf + 2, (rp + width - 1) / width * width);
+            let cost = width * rf + rp;
+            if best.map_or(true, |(c, f, _)| cost < c || (cost == c && rf < f)) {
+                best = Some((cost, rf, rp));
+            }
+        }
+    }
+    best.map(|(_, rf, rp)| (rf, rp)).ok_or(PoseidonError::NoSecureRounds)
+}
+
+fn mat_mul<F: Field>(a: &[Vec<F>], b: &[Vec<F>]) -> Vec<Vec<F>> {
+    let n = a.len();
+    (0..n)
+        .map(|i| (0..n).map(|j| (0..n).fold(F::zero(), |acc, k| acc + a[i][k] * b[k][j])).collect())
+        .collect()
+}
+
+fn is_scalar<F: Field>(a: &[Vec<F>]) -> bool {
+    (0..a.len()).all(|i| (0..a.len()).all(|j| a[i][j] == if i == j { a[0][0] } else { F::zero() }))
+}
+
+fn rank<F: Field>(mut rows: Vec<Vec<F>>) -> usize {
+    let n_cols = rows.first().map_or(0, |r| r.len());
+    let mut rank = 0;
+    for col in 0..n_cols {
+        if let Some(p) = (rank..rows.len()).find(|i| !rows[*i][col].is_zero()) {
+            rows.swap(rank, p);
+            let inv = rows[rank][col].inverse().unwrap();
+            for i in 0..rows.len() {
+                if i != rank && !rows[i][col].is_zero() {
+                    let f = rows[i][col] * inv;
+                    for j in col..n_cols {
+                        let v = rows[rank][j];
+                        rows[i][j] -= f * v;
+                    }
+                }
+            }
+            rank += 1;
+        }
+    }
+    rank
+}
+
+// A partial round has its S-box on cell 0, so a subspace of states with 'x_0 = 0' that 'a'
+// maps into itself would be a trail through any number of partial rounds. The largest one is
+// the kernel of the rows 'e_0 * a^k' for k < t, which is trivial when they have full rank.
+fn no_inactive_invariant<F: Field>(a: &[Vec<F>]) -> bool {
+    let t = a.len();
+    let mut row = vec![F::zero(); t];
+    row[0] = F::one();
+    let mut rows = Vec::with_capacity(t);
+    for _ in 0..t {
+        let next = (0..t).map(|j| (0..t).fold(F::zero(), |acc, k| acc + row[k] * a[k][j])).collect();
+        rows.push(std::mem::replace(&mut row, next));
+    }
+    rank(rows) == t
+}
+
+// algorithm 1: no power M^i for i <= r = t - 1 is a multiple of the identity
+fn algorithm_1<F: Field>(m: &[Vec<F>]) -> bool {
+    let mut power = m.to_vec();
+    for _ in 1..m.len() {
+        if is_scalar(&power) {
+            return false;
+        }
+        power = mat_mul(&power, m);
+    }
+    true
+}
+
+// algorithm 2: M has no invariant subspace with an inactive S-box
+fn algorithm_2<F: Field>(m: &[Vec<F>]) -> bool {
+    no_inactive_invariant(m)
+}
+
+// algorithm 3: neither has M^r for 2 <= r <= 4t, so there is no such trail of period r either
+fn algorithm_3<F: Field>(m: &[Vec<F>]) -> bool {
+    let mut power = m.to_vec();
+    (2..=4 * m.len()).all(|_| {
+        power = mat_mul(&power, m);
+        algorithm_2(&power)
+    })
+}
+
+// whether 'mds' passes the reference's algorithms 1-3 against infinitely long subspace trails
+pub fn mds_is_secure<F: Field>(mds: &[Vec<F>]) -> bool {
+    algorithm_1(mds) && algorithm_2(mds) && algorithm_3(mds)
+}
+
+impl<F: PrimeField> PoseidonParams<F> {
+
+    // parameters with the secure round numbers for 'security_bits'
+    pub fn generate(width: usize, alpha: u64, security_bits: usize) -> Result<Self, PoseidonError> {
+        let (full_rounds, partial_rounds) = round_numbers::<F>(width, alpha, security_bits)?;
+        Self::with_rounds(width, alpha, full_rounds, partial_rounds)
+    }
+
+    // parameters for given round numbers, e.g. to reproduce published tables
+    pub fn with_rounds(width: usize, alpha: u64, full_rounds: usize, partial_rounds: usize) -> Result<Self, PoseidonError> {
+        if width < 2 {
+            return Err(PoseidonError::WidthTooSmall);
+        }
+        check_alpha::<F>(alpha)?;
+
+        let n = field_bits::<F>();
+        let mut grain = Grain::new(n, width, full_rounds, partial_rounds);
+        let ark = (0..full_rounds + partial_rounds)
+            .map(|_| (0..width).map(|_| grain.field_element::<F>(n)).collect())
+            .collect();
+
+        let mds = loop {
+            let xs: Vec<F> = (0..2 * width).map(|_| grain.field_element_mod::<F>(n)).collect();
+            let distinct = (0..xs.len()).all(|i| !xs[..i].contains(&xs[i]));
+            let (xs, ys) = xs.split_at(width);
+            if distinct && xs.iter().all(|x| ys.iter().all(|y| !(*x + y).is_zero())) {
+                let mds: Vec<Vec<F>> = xs
+                    .iter()
+                    .map(|x| ys.iter().map(|y| (*x + y).inverse().unwrap()).collect())
+                    .collect();
+                if mds_is_secure(&mds) {
+                    break mds;
+                }
+            }
+        };
+
+        Ok(Self {
+            width: width,
+            alpha: alpha,
+            full_rounds: full_rounds,
+            partial_rounds: partial_rounds,
+            ark: ark,
+            mds: mds,
+        })
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use bellman_bignat::hash::circuit::CircuitHasher;
+use bellman_bignat::hash::Hasher;
+use rug::Integer;
+use sapling_crypto::bellman::{Index, LinearCombination, Variable};
+use sapling_crypto::circuit::num::AllocatedNum;
+
+use crate::circuit::challenge::alloc_challenge;
+use crate::circuit::order::{OrderBounds, OrderCircuit};
+use crate::circuit::squaring::RepeatedSquaringCircuit;
+use crate::circuit::{alloc_nat, check_params, Circuit, ConstraintSystem, Engine, SynthesisError};
+use crate::homomorphic::PuzzleParams;
+use crate::params::CircuitParams;
+use crate::Delay;
+
+// Constraint costs of the crate's gadgets and circuits.
+//
+// 'ProfilingCs' counts constraints, variables and linear-combination terms, and attributes
+// them to the top-level namespace they were made in, with trailing indices dropped so that
+// e.g. "square 0".."square 9" add up under "square". Inputs of the gadget profiles are
+// allocated under "inputs", apart from the gadget itself.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cost {
+    pub constraints: usize,
+    pub variables: usize,
+    pub inputs: usize,
+    // nonzero terms over the A, B and C combinations of all constraints
+    pub terms: usize,
+}
+
+impl Cost {
+    // mean terms per constraint
+    pub fn density(&self) -> f64 {
+        if self.constraints == 0 {
+            0.0
+        } else {
+            self.terms as f64 / self.constraints as f64
+        }
+    }
+
+    fn add(&mut self, other: &Cost) {
+        self.constraints += other.constraints;
+        self.variables += other.variables;
+        self.inputs += other.inputs;
+        self.terms += other.terms;
+    }
+}
+
+#[derive(Default)]
+pub struct ProfilingCs {
+    path: Vec<String>,
+    scopes: BTreeMap<String, Cost>,
+}
+
+impl ProfilingCs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn scope(&mut self) -> &mut Cost {
+        let name = self
+            .path
+            .first()
+            .map_or("(root)", |n| n.trim_end_matches(|c: char| c.is_ascii_digit() || c == ' '))
+            .to_string();
+        self.scopes.entry(name).or_default()
+    }
+
+    pub fn total(&self) -> Cost {
+        let mut total = Cost::default();
+        for cost in self.scopes.values() {
+            total.add(cost);
+        }
+        total
+    }
+
+    pub fn scopes(&self) -> Vec<(String, Cost)> {
+        self.scopes.iter().map(|(n, c)| (n.clone(), *c)).collect()
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for ProfilingCs {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.scope().variables += 1;
+        Ok(Variable::new_unchecked(Index::Aux(0)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.scope().inputs += 1;
+        Ok(Variable::new_unchecked(Index::Input(0)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        let terms = a(LinearCombination::zero()).as_ref().len()
+            + b(LinearCombination::zero()).as_ref().len()
+            + c(LinearCombination::zero()).as_ref().len();
+        let scope = self.scope();
+        scope.constraints += 1;
+        scope.terms += terms;
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.path.push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self) {
+        self.path.pop();
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Profile {
+    pub name: String,
+    pub total: Cost,
+    pub scopes: Vec<(String, Cost)>,
+}
+
+impl Profile {
+    fn from_cs(name: &str, cs: &ProfilingCs) -> Self {
+        Self {
+            name: name.to_string(),
+            total: cs.total(),
+            scopes: cs.scopes(),
+        }
+    }
+
+    // cost of one top-level namespace
+    pub fn scope(&self, name: &str) -> Option<Cost> {
+        self.scopes.iter().find(|(n, _)| n == name).map(|(_, c)| *c)
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let row = |f: &mut fmt::Formatter<'_>, name: &str, c: &Cost| {
+            writeln!(f, "{:<32} {:>12} {:>12} {:>8} {:>14} {:>8.2}", name, c.constraints, c.variables, c.inputs, c.terms, c.density())
+        };
+        writeln!(f, "{:<32} {:>12} {:>12} {:>8} {:>14} {:>8}", self.name, "constraints", "variables", "inputs", "lc terms", "density")?;
+        for (name, cost) in &self.scopes {
+            row(f, format!("  {}", name).as_str(), cost)?;
+        }
+        row(f, "  total", &self.total)
+    }
+}
+
+// synthesize 'circuit' into a profiling constraint system
+pub fn profile_circuit<E: Engine, C: Circuit<E>>(name: &str, circuit: C) -> Result<Profile, SynthesisError> {
+    let mut cs = ProfilingCs::new();
+    circuit.synthesize(&mut cs)?;
+    Ok(Profile::from_cs(name, &cs))
+}
+
+// the gadgets the circuits are built from, each on inputs in the limb layout of 'params'
+pub fn profile_gadgets<E, H>(hasher: &H, params: &CircuitParams) -> Result<Vec<Profile>, SynthesisError>
+where
+    E: Engine,
+    H: Hasher<F = E::Fr> + CircuitHasher<E = E>,
+{
+    check_params::<E>(params)?;
+    let mut profiles = Vec::new();
+
+    let mut cs = ProfilingCs::new();
+    let a = alloc_nat::<E, _>(cs.namespace(|| "inputs a"), &None, params)?;
+    let b = alloc_nat::<E, _>(cs.namespace(|| "inputs b"), &None, params)?;
+    let m = alloc_nat::<E, _>(cs.namespace(|| "inputs m"), &None, params)?;
+    a.mult_mod(cs.namespace(|| "bignat mult_mod"), &b, &m)?;
+    profiles.push(Profile::from_cs("bignat multiply (mult_mod)", &cs));
+
+    let mut cs = ProfilingCs::new();
+    let wide = CircuitParams {
+        n_limbs: 2 * params.n_limbs,
+        ..*params
+    };
+    let a = alloc_nat::<E, _>(cs.namespace(|| "inputs a"), &None, &wide)?;
+    let m = alloc_nat::<E, _>(cs.namespace(|| "inputs m"), &None, params)?;
+    a.red_mod(cs.namespace(|| "bignat red_mod"), &m)?;
+    profiles.push(Profile::from_cs("modular reduction (red_mod)", &cs));
+
+    let mut cs = ProfilingCs::new();
+    let x = alloc_nat::<E, _>(cs.namespace(|| "inputs x"), &None, params)?;
+    let y = alloc_nat::<E, _>(cs.namespace(|| "inputs y"), &None, params)?;
+    alloc_challenge(cs.namespace(|| "hash-to-prime"), hasher, params, &x, &y)?;
+    profiles.push(Profile::from_cs("hash-to-prime", &cs));
+
+    let mut cs = ProfilingCs::new();
+    let inputs = (0..params.n_limbs)
+        .map(|i| AllocatedNum::<E>::alloc(cs.namespace(|| format!("inputs {}", i)), || Err(SynthesisError::AssignmentMissing)))
+        .collect::<Result<Vec<_>, _>>()?;
+    hasher.allocate_hash(cs.namespace(|| "poseidon"), &inputs)?;
+    profiles.push(Profile::from_cs(format!("poseidon ({} inputs)", params.n_limbs).as_str(), &cs));
+
+    Ok(profiles)
+}
+
+// the crate's circuits at one limb layout; 't' squarings for the repeated squaring circuit
+// and a delay of t for the sealed order
+pub fn profile_circuits<E, H>(hasher: &H, params: &CircuitParams, t: usize) -> Result<Vec<Profile>, SynthesisError>
+where
+    E: Engine,
+    H: Hasher<F = E::Fr> + CircuitHasher<E = E>,
+{
+    let bounds = OrderBounds {
+        price_bits: 32,
+        size_bits: 32,
+    };
+    // the order circuit's shape only depends on the width of its modulus, not on the delay
+    let m = (Integer::from(1usize) << params.modulus_bits as u32) - 1u32;
+    let delay = Delay::new(t as u32).map_err(|_| SynthesisError::Unsatisfiable)?;
+    let puzzles = PuzzleParams {
+        modulus: m.clone(),
+        g: Integer::from(2usize),
+        h: Integer::from(3usize),
+        delay: delay,
+    };
+    Ok(vec![
+        profile_circuit::<E, _>(format!("repeated squaring (t = {})", t).as_str(), RepeatedSquaringCircuit::blank(*params, t))?,
+        profile_circuit::<E, _>(
+            "sealed order",
+            OrderCircuit::<E, H>::blank(&puzzles, hasher.clone(), *params, bounds),
+        )?,
+    ])
+}
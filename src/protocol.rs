@@ -0,0 +1,573 @@
+use std::collections::BTreeMap;
+
+use bls12_381::G1Projective;
+use rand::RngCore;
+use rug::integer::Order;
+use rug::Integer;
+
+use crate::commitment::{self, Commitment, Opening};
+use crate::timed_signature::{verify_signature, Signature, SigningKey};
+use crate::timelock::TimelockError;
+use crate::wesolowski::Proof;
+use crate::{Delay, TrapdoorVDF};
+
+// Commit-reveal order flow between traders and the operator.
+//
+// A trader announces its modulus and delay, then seals orders as timed commitments.
+// The trader reveals with its trapdoor opening; if it does not, the operator forces the
+// opening by sequential squaring and sends the result back, signed, which the trader can
+// dispute with that receipt. Every message carries a Wesolowski proof, but a trader with the
+// trapdoor can prove any output, so openings are judged by the commitment's binding hash of
+// the message ('Commitment::open'), never by the proof alone. A sequence number is
+// used up by any message that passes the sequence check, accepted or not, and the operator
+// keeps all those messages as a transcript that 'audit' replays.
+
+pub type TraderId = u64;
+pub type OrderId = u64;
+
+// base of the calibration evaluation in 'ParamAnnouncement'
+const CALIBRATION_BASE: u32 = 2;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProtocolError {
+    UnknownTrader,
+    AlreadyAnnounced,
+    NotAnnounced,
+    // sequence number already seen
+    Replay,
+    // sequence number skipped ahead
+    OutOfOrder,
+    UnknownOrder,
+    DuplicateOrder,
+    // message not allowed in the current state of the order
+    InvalidTransition,
+    // modulus or delay differ from the announcement
+    ParameterMismatch,
+    // the receipt is not signed by the operator, or its message is the committed one
+    DisputeRejected,
+    // a forced opening not signed by the operator
+    InvalidSignature,
+    // an operator replayed by 'audit' cannot sign forced openings
+    NoSigningKey,
+    Timelock(TimelockError),
+}
+
+impl From<TimelockError> for ProtocolError {
+    fn from(e: TimelockError) -> Self {
+        ProtocolError::Timelock(e)
+    }
+}
+
+// modulus and delay of a trader, with a proven evaluation of the calibration base
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParamAnnouncement {
+    pub trader: TraderId,
+    pub seq: u64,
+    pub modulus: Integer,
+    pub delay: Delay,
+    pub calibration: Proof,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SealedOrder {
+    pub trader: TraderId,
+    pub seq: u64,
+    pub order: OrderId,
+    pub commitment: Commitment,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reveal {
+    pub trader: TraderId,
+    pub seq: u64,
+    pub order: OrderId,
+    pub opening: Opening,
+}
+
+// sent by the operator after opening an order by sequential squaring, and signed so that the
+// trader holds it as a receipt of what the operator claimed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ForcedOpen {
+    pub trader: TraderId,
+    pub seq: u64,
+    pub order: OrderId,
+    pub message: Vec<u8>,
+    pub opening: Opening,
+    pub signature: Signature,
+}
+
+// sent by a trader who disagrees with a forced opening: the signed receipt, and the trader's
+// own opening as evidence of the committed message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dispute {
+    pub trader: TraderId,
+    pub seq: u64,
+    pub order: OrderId,
+    pub message: Vec<u8>,
+    pub opening: Opening,
+    pub receipt: ForcedOpen,
+}
+
+impl ForcedOpen {
+
+    // everything but the signature, length-prefixed
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for v in [self.trader, self.seq, self.order] {
+            bytes.extend_from_slice(&v.to_be_bytes());
+        }
+        let output = self.opening.output.to_digits::<u8>(Order::Msf);
+        let pi = self.opening.pi.to_digits::<u8>(Order::Msf);
+        for field in [&self.message, &output, &pi] {
+            bytes.extend_from_slice(&(field.len() as u64).to_be_bytes());
+            bytes.extend_from_slice(field);
+        }
+        bytes
+    }
+
+    pub fn verify(&self, operator: &G1Projective) -> bool {
+        verify_signature(operator, &self.signed_bytes(), &self.signature)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Announce(ParamAnnouncement),
+    Seal(SealedOrder),
+    Reveal(Reveal),
+    ForcedOpen(ForcedOpen),
+    Dispute(Dispute),
+}
+
+impl Message {
+    pub fn trader(&self) -> TraderId {
+        match self {
+            Message::Announce(m) => m.trader,
+            Message::Seal(m) => m.trader,
+            Message::Reveal(m) => m.trader,
+            Message::ForcedOpen(m) => m.trader,
+            Message::Dispute(m) => m.trader,
+        }
+    }
+
+    pub fn seq(&self) -> u64 {
+        match self {
+            Message::Announce(m) => m.seq,
+            Message::Seal(m) => m.seq,
+            Message::Reveal(m) => m.seq,
+            Message::ForcedOpen(m) => m.seq,
+            Message::Dispute(m) => m.seq,
+        }
+    }
+}
+
+// sequence numbers start at 1 and must follow each other without gaps
+fn check_seq(last: u64, seq: u64) -> Result<(), ProtocolError> {
+    if seq <= last {
+        Err(ProtocolError::Replay)
+    } else if seq > last + 1 {
+        Err(ProtocolError::OutOfOrder)
+    } else {
+        Ok(())
+    }
+}
+
+fn check_calibration(a: &ParamAnnouncement) -> bool {
+    let vdf = TrapdoorVDF::setup("2", a.modulus.to_string_radix(10).as_str());
+    vdf.verify_delay(&Integer::from(CALIBRATION_BASE), &a.delay, &a.calibration)
+}
+
+//== Trader side ==//
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TraderOrderState {
+    Sealed,
+    Revealed,
+    ForceOpened,
+    Disputed,
+}
+
+struct TraderOrder {
+    commitment: Commitment,
+    opening: Opening,
+    message: Vec<u8>,
+    state: TraderOrderState,
+}
+
+pub struct Trader {
+    pub id: TraderId,
+    vdf: TrapdoorVDF,
+    delay: Delay,
+    // public key of the operator, for the receipts of forced openings
+    operator: G1Projective,
+    announced: bool,
+    seq: u64,
+    operator_seq: u64,
+    orders: BTreeMap<OrderId, TraderOrder>,
+}
+
+impl Trader {
+
+    pub fn new(id: TraderId, vdf: TrapdoorVDF, delay: Delay, operator: G1Projective) -> Self {
+        Self {
+            id: id,
+            vdf: vdf,
+            delay: delay,
+            operator: operator,
+            announced: false,
+            seq: 0,
+            operator_seq: 0,
+            orders: BTreeMap::new(),
+        }
+    }
+
+    fn next_seq(&mut self) -> u64 {
+        self.seq += 1;
+        self.seq
+    }
+
+    pub fn order_state(&self, order: OrderId) -> Option<&TraderOrderState> {
+        self.orders.get(&order).map(|o| &o.state)
+    }
+
+    pub fn announce(&mut self) -> Result<Message, ProtocolError> {
+        if self.announced {
+            return Err(ProtocolError::AlreadyAnnounced);
+        }
+        if !self.vdf.has_trapdoor() {
+            return Err(TimelockError::MissingTrapdoor.into());
+        }
+        self.announced = true;
+
+        let calibration = self.vdf.prove_delay_with_trapdoor(&Integer::from(CALIBRATION_BASE), &self.delay);
+        Ok(Message::Announce(ParamAnnouncement {
+            trader: self.id,
+            seq: self.next_seq(),
+            modulus: self.vdf.group.m.clone(),
+            delay: self.delay.clone(),
+            calibration: calibration,
+        }))
+    }
+
+    pub fn seal(&mut self, order: OrderId, message: &[u8]) -> Result<Message, ProtocolError> {
+        self.seal_with_rng(order, message, &mut rand::thread_rng())
+    }
+
+    // same as 'seal' with the commitment randomness drawn from 'rng'
+    pub fn seal_with_rng<R: RngCore>(&mut self, order: OrderId, message: &[u8], rng: &mut R) -> Result<Message, ProtocolError> {
+        if !self.announced {
+            return Err(ProtocolError::NotAnnounced);
+        }
+        if self.orders.contains_key(&order) {
+            return Err(ProtocolError::DuplicateOrder);
+        }
+        let (c, opening) = commitment::commit_with_rng(&self.vdf, &self.delay, message, rng)?;
+        self.orders.insert(order, TraderOrder {
+            commitment: c.clone(),
+            opening: opening,
+            message: message.to_vec(),
+            state: TraderOrderState::Sealed,
+        });
+
+        Ok(Message::Seal(SealedOrder {
+            trader: self.id,
+            seq: self.next_seq(),
+            order: order,
+            commitment: c,
+        }))
+    }
+
+    pub fn reveal(&mut self, order: OrderId) -> Result<Message, ProtocolError> {
+        let o = self.orders.get_mut(&order).ok_or(ProtocolError::UnknownOrder)?;
+        if o.state != TraderOrderState::Sealed {
+            return Err(ProtocolError::InvalidTransition);
+        }
+        o.state = TraderOrderState::Revealed;
+        let opening = o.opening.clone();
+
+        Ok(Message::Reveal(Reveal {
+            trader: self.id,
+            seq: self.next_seq(),
+            order: order,
+            opening: opening,
+        }))
+    }
+
+    // handle a message from the operator; returns the dispute to send back, if any
+    pub fn handle(&mut self, msg: &Message) -> Result<Option<Message>, ProtocolError> {
+        let f = match msg {
+            Message::ForcedOpen(f) if f.trader == self.id => f,
+            Message::ForcedOpen(_) => return Err(ProtocolError::UnknownTrader),
+            _ => return Err(ProtocolError::InvalidTransition),
+        };
+        if !f.verify(&self.operator) {
+            return Err(ProtocolError::InvalidSignature);
+        }
+        check_seq(self.operator_seq, f.seq)?;
+        self.operator_seq = f.seq;
+        let o = self.orders.get_mut(&f.order).ok_or(ProtocolError::UnknownOrder)?;
+        if o.state != TraderOrderState::Sealed {
+            return Err(ProtocolError::InvalidTransition);
+        }
+
+        let honest = o.commitment.open(&f.opening).map_or(false, |m| m == f.message);
+        if honest {
+            o.state = TraderOrderState::ForceOpened;
+            return Ok(None);
+        }
+
+        o.state = TraderOrderState::Disputed;
+        let message = o.message.clone();
+        let opening = o.opening.clone();
+
+        Ok(Some(Message::Dispute(Dispute {
+            trader: self.id,
+            seq: self.next_seq(),
+            order: f.order,
+            message: message,
+            opening: opening,
+            receipt: f.clone(),
+        })))
+    }
+}
+
+//== Operator side ==//
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OrderState {
+    Sealed,
+    Revealed(Vec<u8>),
+    ForceOpened(Vec<u8>),
+    // message established by an upheld dispute
+    Disputed(Vec<u8>),
+}
+
+// accepted transitions reported by 'Operator::handle'
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Announced(TraderId),
+    Sealed(TraderId, OrderId),
+    Revealed(TraderId, OrderId, Vec<u8>),
+    ForceOpened(TraderId, OrderId, Vec<u8>),
+    Disputed(TraderId, OrderId, Vec<u8>),
+}
+
+struct OrderRecord {
+    commitment: Commitment,
+    state: OrderState,
+}
+
+struct TraderRecord {
+    modulus: Integer,
+    delay: Delay,
+    seq: u64,
+    operator_seq: u64,
+    orders: BTreeMap<OrderId, OrderRecord>,
+}
+
+pub struct Operator {
+    // signs forced openings; missing in an operator replayed by 'audit'
+    key: Option<SigningKey>,
+    pk: G1Projective,
+    traders: BTreeMap<TraderId, TraderRecord>,
+    transcript: Vec<Message>,
+}
+
+impl Operator {
+
+    pub fn new() -> Self {
+        Self::with_key(SigningKey::generate())
+    }
+
+    pub fn with_key(key: SigningKey) -> Self {
+        Self {
+            pk: key.pk,
+            key: Some(key),
+            traders: BTreeMap::new(),
+            transcript: Vec::new(),
+        }
+    }
+
+    pub fn public_key(&self) -> &G1Projective {
+        &self.pk
+    }
+
+    pub fn transcript(&self) -> &[Message] {
+        &self.transcript
+    }
+
+    pub fn order_state(&self, trader: TraderId, order: OrderId) -> Option<&OrderState> {
+        self.traders.get(&trader)?.orders.get(&order).map(|o| &o.state)
+    }
+
+    // validate and apply a message from a trader. Once its sequence number is checked the
+    // message uses it up and goes into the transcript, even if it is then rejected, so the
+    // trader's next message is still in order.
+    pub fn handle(&mut self, msg: &Message) -> Result<Event, ProtocolError> {
+        let a = match msg {
+            Message::Announce(a) => a,
+            Message::ForcedOpen(_) => return Err(ProtocolError::InvalidTransition),
+            _ => {
+                let t = self.traders.get_mut(&msg.trader()).ok_or(ProtocolError::UnknownTrader)?;
+                check_seq(t.seq, msg.seq())?;
+                t.seq = msg.seq();
+                self.transcript.push(msg.clone());
+                return match msg {
+                    Message::Seal(s) => self.on_seal(s),
+                    Message::Reveal(r) => self.on_reveal(r),
+                    Message::Dispute(d) => self.on_dispute(d),
+                    _ => unreachable!(),
+                };
+            }
+        };
+        let event = self.on_announce(a)?;
+        self.transcript.push(msg.clone());
+        Ok(event)
+    }
+
+    // open a sealed order by sequential squaring; the result is sent to the trader
+    pub fn force_open(&mut self, trader: TraderId, order: OrderId) -> Result<Message, ProtocolError> {
+        let key = self.key.as_ref().ok_or(ProtocolError::NoSigningKey)?;
+        let t = self.traders.get_mut(&trader).ok_or(ProtocolError::UnknownTrader)?;
+        let o = t.orders.get_mut(&order).ok_or(ProtocolError::UnknownOrder)?;
+        if o.state != OrderState::Sealed {
+            return Err(ProtocolError::InvalidTransition);
+        }
+        let (message, opening) = o.commitment.force_open()?;
+        o.state = OrderState::ForceOpened(message.clone());
+        t.operator_seq += 1;
+
+        let mut f = ForcedOpen {
+            trader: trader,
+            seq: t.operator_seq,
+            order: order,
+            message: message,
+            opening: opening,
+            signature: Signature::default(),
+        };
+        f.signature = key.sign(&f.signed_bytes());
+        let msg = Message::ForcedOpen(f);
+        self.transcript.push(msg.clone());
+        Ok(msg)
+    }
+
+    // replay the transcript of the operator with public key 'pk', checking every transition,
+    // proof and signature, including the operator's own forced openings. Messages rejected
+    // after their sequence check must be rejected again; any other failure fails the audit.
+    pub fn audit(pk: &G1Projective, transcript: &[Message]) -> Result<Operator, ProtocolError> {
+        let mut op = Operator {
+            key: None,
+            pk: *pk,
+            traders: BTreeMap::new(),
+            transcript: Vec::new(),
+        };
+        for msg in transcript {
+            match msg {
+                Message::ForcedOpen(f) => {
+                    if !f.verify(pk) {
+                        return Err(ProtocolError::InvalidSignature);
+                    }
+                    op.on_forced_open(f)?;
+                    op.transcript.push(msg.clone());
+                }
+                _ => {
+                    let len = op.transcript.len();
+                    if let Err(e) = op.handle(msg) {
+                        if op.transcript.len() == len {
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(op)
+    }
+
+    // a trader whose message already passed 'check_seq' in 'handle'
+    fn trader_mut(&mut self, trader: TraderId) -> &mut TraderRecord {
+        self.traders.get_mut(&trader).unwrap()
+    }
+
+    fn on_announce(&mut self, a: &ParamAnnouncement) -> Result<Event, ProtocolError> {
+        if self.traders.contains_key(&a.trader) {
+            return Err(ProtocolError::AlreadyAnnounced);
+        }
+        check_seq(0, a.seq)?;
+        if !check_calibration(a) {
+            return Err(TimelockError::InvalidProof.into());
+        }
+        self.traders.insert(a.trader, TraderRecord {
+            modulus: a.modulus.clone(),
+            delay: a.delay.clone(),
+            seq: a.seq,
+            operator_seq: 0,
+            orders: BTreeMap::new(),
+        });
+        Ok(Event::Announced(a.trader))
+    }
+
+    fn on_seal(&mut self, s: &SealedOrder) -> Result<Event, ProtocolError> {
+        let t = self.trader_mut(s.trader);
+        if t.orders.contains_key(&s.order) {
+            return Err(ProtocolError::DuplicateOrder);
+        }
+        if s.commitment.modulus != t.modulus || s.commitment.delay != t.delay {
+            return Err(ProtocolError::ParameterMismatch);
+        }
+        if !s.commitment.verify() {
+            return Err(TimelockError::InvalidProof.into());
+        }
+        t.orders.insert(s.order, OrderRecord {
+            commitment: s.commitment.clone(),
+            state: OrderState::Sealed,
+        });
+        Ok(Event::Sealed(s.trader, s.order))
+    }
+
+    fn on_reveal(&mut self, r: &Reveal) -> Result<Event, ProtocolError> {
+        let t = self.trader_mut(r.trader);
+        let o = t.orders.get_mut(&r.order).ok_or(ProtocolError::UnknownOrder)?;
+        if o.state != OrderState::Sealed {
+            return Err(ProtocolError::InvalidTransition);
+        }
+        let message = o.commitment.open(&r.opening)?;
+        o.state = OrderState::Revealed(message.clone());
+        Ok(Event::Revealed(r.trader, r.order, message))
+    }
+
+    fn on_forced_open(&mut self, f: &ForcedOpen) -> Result<Event, ProtocolError> {
+        let t = self.traders.get_mut(&f.trader).ok_or(ProtocolError::UnknownTrader)?;
+        check_seq(t.operator_seq, f.seq)?;
+        let o = t.orders.get_mut(&f.order).ok_or(ProtocolError::UnknownOrder)?;
+        if o.state != OrderState::Sealed {
+            return Err(ProtocolError::InvalidTransition);
+        }
+        if o.commitment.open(&f.opening)? != f.message {
+            return Err(TimelockError::InvalidProof.into());
+        }
+        o.state = OrderState::ForceOpened(f.message.clone());
+        t.operator_seq = f.seq;
+        Ok(Event::ForceOpened(f.trader, f.order, f.message.clone()))
+    }
+
+    // a dispute is upheld when the operator signed a receipt for this order with a message
+    // other than the one bound by the commitment, which the trader's opening decodes
+    fn on_dispute(&mut self, d: &Dispute) -> Result<Event, ProtocolError> {
+        let pk = self.pk;
+        let t = self.trader_mut(d.trader);
+        let o = t.orders.get_mut(&d.order).ok_or(ProtocolError::UnknownOrder)?;
+        if !matches!(o.state, OrderState::ForceOpened(_)) {
+            return Err(ProtocolError::InvalidTransition);
+        }
+        let r = &d.receipt;
+        if r.trader != d.trader || r.order != d.order || !r.verify(&pk) {
+            return Err(ProtocolError::DisputeRejected);
+        }
+        let message = o.commitment.open(&d.opening)?;
+        if message != d.message || message == r.message {
+            return Err(ProtocolError::DisputeRejected);
+        }
+        o.state = OrderState::Disputed(message.clone());
+        Ok(Event::Disputed(d.trader, d.order, message))
+    }
+}
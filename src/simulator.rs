@@ -0,0 +1,449 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::protocol::{Event, Message, Operator, OrderId, OrderState, ProtocolError, Trader, TraderId, TraderOrderState};
+use crate::timed_signature::SigningKey;
+use crate::{Delay, TrapdoorVDF};
+
+// copies the colluding trader seals for one order read early
+const GRIND_TRIES: usize = 16;
+
+// In-process fair-ordering simulation of the commit-reveal protocol.
+//
+// Time is a simulated clock in ticks, and a scheduler delivers every message between the real
+// 'Trader' and 'Operator' state machines after a sampled network latency. An order belongs to
+// the batch window in which the operator receives its seal. When the window closes its seals
+// are sequenced by their commitment's binding hash, which neither the arrival time nor the
+// operator decides. Traders then reveal, or leave the order to be forced.
+//
+// An adversarial operator starts forcing every sealed order as soon as it arrives. When the
+// opening completes before the order's batch closes, the operator reads the order and has a
+// colluding trader seal copies into the same batch, up to 'GRIND_TRIES' of them, until one
+// sorts ahead of it. The order is front-run only when that copy is sequenced ahead of it.
+// Every random choice, including the keys, moduli and commitments, comes from one RNG seeded
+// with 'SimConfig::seed'.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OperatorKind {
+    Honest,
+    Adversarial,
+}
+
+#[derive(Clone, Debug)]
+pub struct SimConfig {
+    pub traders: usize,
+    pub orders_per_trader: usize,
+    pub modulus_bits: usize,
+    pub delay: Delay,
+    // simulated duration of evaluating 'delay' on honest hardware
+    pub delay_ticks: u64,
+    // squaring speed of the adversary relative to honest hardware
+    pub adversary_speed: f64,
+    pub batch_window: u64,
+    pub batches: u64,
+    // one-way message latency, sampled uniformly from the range
+    pub network_latency: (u64, u64),
+    // probability that a trader reveals instead of leaving the order to be forced
+    pub reveal_rate: f64,
+    pub operator: OperatorKind,
+    pub seed: u64,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            traders: 4,
+            orders_per_trader: 4,
+            modulus_bits: 512,
+            delay: Delay::new(12).unwrap(),
+            delay_ticks: 1_000,
+            adversary_speed: 1.0,
+            batch_window: 500,
+            batches: 2,
+            network_latency: (5, 50),
+            reveal_rate: 0.75,
+            operator: OperatorKind::Honest,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LatencyStats {
+    pub count: usize,
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    pub p50: u64,
+    pub p99: u64,
+}
+
+impl LatencyStats {
+    fn from_samples(mut samples: Vec<u64>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_unstable();
+        let n = samples.len();
+        let at = |q: f64| samples[(((n - 1) as f64) * q).round() as usize];
+        Self {
+            count: n,
+            min: samples[0],
+            max: samples[n - 1],
+            mean: samples.iter().sum::<u64>() as f64 / n as f64,
+            p50: at(0.5),
+            p99: at(0.99),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Report {
+    // orders of the honest traders
+    pub orders: usize,
+    // orders the operator opened before their batch closed
+    pub early_reads: usize,
+    // orders sequenced behind a copy sealed by the colluding trader
+    pub violations: usize,
+    // messages the state machines rejected, e.g. a reveal racing a forced opening
+    pub rejected: usize,
+    // from submission to the order being readable by the operator, in ticks
+    pub reveal_latency: LatencyStats,
+    pub forced_latency: LatencyStats,
+    // wall-clock time spent sealing and force-opening
+    pub seal_time: Duration,
+    pub force_time: Duration,
+}
+
+enum Action {
+    Submit(TraderId, OrderId),
+    ToOperator(Message),
+    ToTrader(Message),
+    // the adversary's forced opening started on arrival completes
+    EarlyForce(TraderId, OrderId),
+    CloseBatch(u64),
+    Reveal(TraderId, OrderId),
+    // the operator's forced opening of a withheld order completes
+    Force(TraderId, OrderId),
+}
+
+struct SimOrder {
+    trader: TraderId,
+    order: OrderId,
+    submitted: u64,
+    // batch of the seal at the operator, once received
+    batch: Option<u64>,
+    readable: Option<u64>,
+}
+
+pub struct Simulator {
+    config: SimConfig,
+    rng: StdRng,
+    now: u64,
+    traders: Vec<Trader>,
+    // trader colluding with an adversarial operator
+    accomplice: Trader,
+    operator: Operator,
+    // pending actions by (tick, scheduling order)
+    queue: BTreeMap<(u64, u64), Action>,
+    scheduled: u64,
+    orders: Vec<SimOrder>,
+    // seals received in each batch with their commitment's binding hash, in arrival order
+    batches: Vec<Vec<([u8; 32], TraderId, OrderId)>>,
+    // (victim, last copy) for every order read before its batch closed
+    front_runs: Vec<((TraderId, OrderId), OrderId)>,
+    // orders sealed by the colluding trader
+    copies: OrderId,
+    // final order sequence of each closed batch
+    sequences: Vec<Vec<(TraderId, OrderId)>>,
+    rejected: usize,
+    seal_time: Duration,
+    force_time: Duration,
+}
+
+impl Simulator {
+
+    pub fn new(config: SimConfig) -> Result<Self, ProtocolError> {
+        let mut rng = StdRng::seed_from_u64(config.seed);
+        let mut operator = Operator::with_key(SigningKey::generate_with_rng(&mut rng));
+        let mut traders = Vec::with_capacity(config.traders + 1);
+        for id in 0..=config.traders as TraderId {
+            let vdf = TrapdoorVDF::setup_with_rng("2", config.modulus_bits, &mut rng);
+            let mut trader = Trader::new(id, vdf, config.delay.clone(), *operator.public_key());
+            operator.handle(&trader.announce()?)?;
+            traders.push(trader);
+        }
+        let accomplice = traders.pop().unwrap();
+
+        Ok(Self {
+            rng: rng,
+            config: config,
+            now: 0,
+            traders: traders,
+            accomplice: accomplice,
+            operator: operator,
+            queue: BTreeMap::new(),
+            scheduled: 0,
+            orders: Vec::new(),
+            batches: Vec::new(),
+            front_runs: Vec::new(),
+            copies: 0,
+            sequences: Vec::new(),
+            rejected: 0,
+            seal_time: Duration::default(),
+            force_time: Duration::default(),
+        })
+    }
+
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    pub fn operator(&self) -> &Operator {
+        &self.operator
+    }
+
+    // final order sequence of each closed batch
+    pub fn sequences(&self) -> &[Vec<(TraderId, OrderId)>] {
+        &self.sequences
+    }
+
+    fn latency(&mut self) -> u64 {
+        let (lo, hi) = self.config.network_latency;
+        self.rng.gen_range(lo..=hi.max(lo))
+    }
+
+    fn window(&self) -> u64 {
+        self.config.batch_window.max(1)
+    }
+
+    // ties at the same tick run in scheduling order
+    fn schedule(&mut self, tick: u64, action: Action) {
+        self.scheduled += 1;
+        self.queue.insert((tick, self.scheduled), action);
+    }
+
+    fn send(&mut self, action: fn(Message) -> Action, msg: Message) {
+        let arrival = self.now + self.latency();
+        self.schedule(arrival, action(msg));
+    }
+
+    fn order_mut(&mut self, trader: TraderId, order: OrderId) -> &mut SimOrder {
+        self.orders
+            .iter_mut()
+            .find(|o| o.trader == trader && o.order == order)
+            .unwrap()
+    }
+
+    fn sealed(&self, trader: TraderId, order: OrderId) -> bool {
+        self.operator.order_state(trader, order) == Some(&OrderState::Sealed)
+    }
+
+    fn force(&mut self, trader: TraderId, order: OrderId) -> Result<(), ProtocolError> {
+        let start = Instant::now();
+        let forced = self.operator.force_open(trader, order)?;
+        self.force_time += start.elapsed();
+        self.order_mut(trader, order).readable = Some(self.now);
+        self.send(Action::ToTrader, forced);
+        Ok(())
+    }
+
+    // the state machines' own errors are outcomes of the schedule, not failures of the run
+    fn count(&mut self, result: Result<(), ProtocolError>) -> Result<(), ProtocolError> {
+        match result {
+            Err(ProtocolError::Timelock(e)) => Err(ProtocolError::Timelock(e)),
+            Err(_) => {
+                self.rejected += 1;
+                Ok(())
+            }
+            Ok(()) => Ok(()),
+        }
+    }
+
+    fn step(&mut self, action: Action) -> Result<(), ProtocolError> {
+        match action {
+            Action::Submit(trader, order) => {
+                let side = if self.rng.gen_bool(0.5) { "BUY" } else { "SELL" };
+                let text = format!("{} {} @ {} #{}-{}", side, self.rng.gen_range(1..100), self.rng.gen_range(900..1100), trader, order);
+                let start = Instant::now();
+                let msg = self.traders[trader as usize].seal_with_rng(order, text.as_bytes(), &mut self.rng)?;
+                self.seal_time += start.elapsed();
+                self.send(Action::ToOperator, msg);
+            }
+            Action::ToOperator(msg) => {
+                let event = self.operator.handle(&msg);
+                if let (Ok(Event::Sealed(trader, order)), Message::Seal(s)) = (&event, &msg) {
+                    let (trader, order) = (*trader, *order);
+                    let batch = self.now / self.window();
+                    self.order_mut(trader, order).batch = Some(batch);
+                    self.batches[batch as usize].push((s.commitment.binding, trader, order));
+                    if self.config.operator == OperatorKind::Adversarial {
+                        let ticks = (self.config.delay_ticks as f64 / self.config.adversary_speed.max(f64::MIN_POSITIVE)) as u64;
+                        self.schedule(self.now + ticks, Action::EarlyForce(trader, order));
+                    }
+                }
+                if let Ok(Event::Revealed(trader, order, _)) = event {
+                    self.order_mut(trader, order).readable = Some(self.now);
+                }
+                return self.count(event.map(|_| ()));
+            }
+            Action::ToTrader(msg) => {
+                let trader = msg.trader() as usize;
+                let dispute = self.traders[trader].handle(&msg);
+                if let Ok(Some(d)) = &dispute {
+                    self.send(Action::ToOperator, d.clone());
+                }
+                return self.count(dispute.map(|_| ()));
+            }
+            Action::EarlyForce(trader, order) => {
+                let batch = self.order_mut(trader, order).batch.unwrap();
+                let open = (batch + 1) * self.window() > self.now;
+                if open && self.sealed(trader, order) {
+                    self.force(trader, order)?;
+                    // the accomplice copies the order it just read, straight to the operator,
+                    // until a copy sorts ahead of it
+                    let arrival = (self.now / self.window()) as usize;
+                    let key = self.batches[batch as usize]
+                        .iter()
+                        .find(|(_, t, o)| (*t, *o) == (trader, order))
+                        .map(|(k, _, _)| *k)
+                        .unwrap();
+                    let mut last = None;
+                    for _ in 0..GRIND_TRIES {
+                        let copy = self.copies;
+                        self.copies += 1;
+                        let message = format!("COPY #{}-{}", trader, order);
+                        let seal = self.accomplice.seal_with_rng(copy, message.as_bytes(), &mut self.rng)?;
+                        let binding = match (&seal, self.operator.handle(&seal)) {
+                            (Message::Seal(s), Ok(_)) => s.commitment.binding,
+                            _ => break,
+                        };
+                        self.batches[arrival].push((binding, self.accomplice.id, copy));
+                        last = Some(copy);
+                        if binding < key {
+                            break;
+                        }
+                    }
+                    if let Some(copy) = last {
+                        self.front_runs.push(((trader, order), copy));
+                    }
+                }
+            }
+            Action::CloseBatch(batch) => {
+                let mut sequence = Vec::new();
+                let mut arrived = std::mem::take(&mut self.batches[batch as usize]);
+                arrived.sort();
+                for (_, trader, order) in arrived {
+                    sequence.push((trader, order));
+                    // the accomplice's copies are never revealed
+                    if trader == self.accomplice.id {
+                        continue;
+                    }
+
+                    if self.rng.gen_bool(self.config.reveal_rate.clamp(0.0, 1.0)) {
+                        self.schedule(self.now, Action::Reveal(trader, order));
+                    } else {
+                        // the operator waits the longest network latency for a reveal, then
+                        // evaluates the delay
+                        let done = self.now + self.config.network_latency.1 + self.config.delay_ticks;
+                        self.schedule(done, Action::Force(trader, order));
+                    }
+                }
+                self.sequences.push(sequence);
+            }
+            Action::Reveal(trader, order) => {
+                // an order the operator already forced open is not revealed
+                let t = &mut self.traders[trader as usize];
+                if t.order_state(order) == Some(&TraderOrderState::Sealed) {
+                    let msg = t.reveal(order)?;
+                    self.send(Action::ToOperator, msg);
+                }
+            }
+            Action::Force(trader, order) => {
+                if self.sealed(trader, order) {
+                    self.force(trader, order)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn run(&mut self) -> Result<Report, ProtocolError> {
+        let span = self.window() * self.config.batches;
+        // seals still in flight at the last close land in later batches
+        let last = (span + self.config.network_latency.1) / self.window();
+        self.batches = vec![Vec::new(); last as usize + 1];
+
+        for t in 0..self.traders.len() as TraderId {
+            for k in 0..self.config.orders_per_trader as OrderId {
+                let submitted = self.rng.gen_range(0..span);
+                self.orders.push(SimOrder {
+                    trader: t,
+                    order: k,
+                    submitted: submitted,
+                    batch: None,
+                    readable: None,
+                });
+                self.schedule(submitted, Action::Submit(t, k));
+            }
+        }
+        for b in 0..=last {
+            self.schedule((b + 1) * self.window(), Action::CloseBatch(b));
+        }
+
+        while let Some(((tick, _), action)) = self.queue.pop_first() {
+            self.now = self.now.max(tick);
+            self.step(action)?;
+        }
+
+        let mut reveal_latency = Vec::new();
+        let mut forced_latency = Vec::new();
+        let mut early_reads = 0;
+        for o in &self.orders {
+            let readable = match o.readable {
+                Some(r) => r,
+                None => continue,
+            };
+            match self.operator.order_state(o.trader, o.order) {
+                Some(OrderState::Revealed(_)) => reveal_latency.push(readable - o.submitted),
+                Some(_) => forced_latency.push(readable - o.submitted),
+                None => {}
+            }
+            if o.batch.map_or(false, |b| readable < (b + 1) * self.window()) {
+                early_reads += 1;
+            }
+        }
+
+        // a victim is front-run when its copy is sequenced ahead of it in the same batch
+        let violations = self
+            .front_runs
+            .iter()
+            .filter(|(victim, copy)| {
+                self.sequences.iter().any(|seq| {
+                    let v = seq.iter().position(|o| o == victim);
+                    let c = seq.iter().position(|o| *o == (self.accomplice.id, *copy));
+                    matches!((c, v), (Some(c), Some(v)) if c < v)
+                })
+            })
+            .count();
+
+        Ok(Report {
+            orders: self.orders.len(),
+            early_reads: early_reads,
+            violations: violations,
+            rejected: self.rejected,
+            reveal_latency: LatencyStats::from_samples(reveal_latency),
+            forced_latency: LatencyStats::from_samples(forced_latency),
+            seal_time: self.seal_time,
+            force_time: self.force_time,
+        })
+    }
+}
+
+// run one simulation with 'config'
+pub fn simulate(config: SimConfig) -> Result<Report, ProtocolError> {
+    Simulator::new(config)?.run()
+}
@@ -0,0 +1,198 @@
+use std::fmt::Write;
+
+use rug::integer::Order;
+use rug::Integer;
+use sapling_crypto::bellman::groth16::{Proof, VerifyingKey};
+use sapling_crypto::bellman::pairing::bn256::{Bn256, Fq2, Fr, G1Affine, G2Affine};
+use sapling_crypto::bellman::pairing::ff::{PrimeField, PrimeFieldRepr};
+use sapling_crypto::bellman::pairing::{CurveAffine, CurveProjective};
+use sha3::{Digest, Keccak256};
+
+// Solidity verifiers for Groth16 proofs over BN254 ('Bn256').
+//
+// The contract checks 'e(-A, B) * e(α, β) * e(vk_x, γ) * e(C, δ) = 1' with the ecAdd, ecMul
+// and pairing precompiles (0x06, 0x07, 0x08), where 'vk_x = IC_0 + sum input_i * IC_(i+1)'.
+// G2 points are laid out as the precompiles expect: '(x.c1, x.c0, y.c1, y.c0)'.
+
+const BN254_R: &str = "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+const BN254_Q: &str = "21888242871839275222246405745257275088696311157297823662689037894645226208583";
+
+// 32-byte big-endian word of a field element
+fn word<F: PrimeField>(f: &F) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    f.into_repr().write_be(&mut out[..]).unwrap();
+    out
+}
+
+fn decimal<F: PrimeField>(f: &F) -> String {
+    Integer::from_digits(&word(f), Order::Msf).to_string()
+}
+
+fn g1_words(p: &G1Affine) -> Vec<[u8; 32]> {
+    let (x, y) = p.into_xy_unchecked();
+    vec![word(&x), word(&y)]
+}
+
+fn g2_words(p: &G2Affine) -> Vec<[u8; 32]> {
+    let (x, y): (Fq2, Fq2) = p.into_xy_unchecked();
+    vec![word(&x.c1), word(&x.c0), word(&y.c1), word(&y.c0)]
+}
+
+pub fn function_signature(n_inputs: usize) -> String {
+    format!("verifyProof(uint256[2],uint256[2][2],uint256[2],uint256[{}])", n_inputs)
+}
+
+pub fn selector(n_inputs: usize) -> [u8; 4] {
+    let hash = Keccak256::digest(function_signature(n_inputs).as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+// calldata of 'verifyProof' for 'proof' and its public inputs
+pub fn encode_calldata(proof: &Proof<Bn256>, inputs: &[Fr]) -> Vec<u8> {
+    let mut words = g1_words(&proof.a);
+    words.extend(g2_words(&proof.b));
+    words.extend(g1_words(&proof.c));
+    words.extend(inputs.iter().map(word));
+
+    let mut data = selector(inputs.len()).to_vec();
+    for w in words {
+        data.extend_from_slice(&w);
+    }
+    data
+}
+
+// input of the pairing precompile that the verifier builds from 'proof' and 'inputs'
+pub fn pairing_input(vk: &VerifyingKey<Bn256>, proof: &Proof<Bn256>, inputs: &[Fr]) -> Vec<u8> {
+    assert_eq!(inputs.len() + 1, vk.ic.len());
+    let mut vk_x = vk.ic[0].into_projective();
+    for (input, ic) in inputs.iter().zip(vk.ic.iter().skip(1)) {
+        vk_x.add_assign(&ic.mul(input.into_repr()));
+    }
+    let mut neg_a = proof.a;
+    neg_a.negate();
+
+    let mut words = g1_words(&neg_a);
+    words.extend(g2_words(&proof.b));
+    words.extend(g1_words(&vk.alpha_g1));
+    words.extend(g2_words(&vk.beta_g2));
+    words.extend(g1_words(&vk_x.into_affine()));
+    words.extend(g2_words(&vk.gamma_g2));
+    words.extend(g1_words(&proof.c));
+    words.extend(g2_words(&vk.delta_g2));
+    words.concat()
+}
+
+fn g1_constants(out: &mut String, name: &str, p: &G1Affine) {
+    let (x, y) = p.into_xy_unchecked();
+    writeln!(out, "    uint256 constant {}_X = {};", name, decimal(&x)).unwrap();
+    writeln!(out, "    uint256 constant {}_Y = {};", name, decimal(&y)).unwrap();
+}
+
+fn g2_constants(out: &mut String, name: &str, p: &G2Affine) {
+    let (x, y): (Fq2, Fq2) = p.into_xy_unchecked();
+    writeln!(out, "    uint256 constant {}_X1 = {};", name, decimal(&x.c1)).unwrap();
+    writeln!(out, "    uint256 constant {}_X0 = {};", name, decimal(&x.c0)).unwrap();
+    writeln!(out, "    uint256 constant {}_Y1 = {};", name, decimal(&y.c1)).unwrap();
+    writeln!(out, "    uint256 constant {}_Y0 = {};", name, decimal(&y.c0)).unwrap();
+}
+
+// self-contained Solidity source of a contract 'name' that verifies proofs under 'vk'
+pub fn export_verifier(vk: &VerifyingKey<Bn256>, name: &str) -> String {
+    let n = vk.ic.len() - 1;
+    let mut constants = String::new();
+    g1_constants(&mut constants, "ALPHA", &vk.alpha_g1);
+    g2_constants(&mut constants, "BETA", &vk.beta_g2);
+    g2_constants(&mut constants, "GAMMA", &vk.gamma_g2);
+    g2_constants(&mut constants, "DELTA", &vk.delta_g2);
+    for (i, ic) in vk.ic.iter().enumerate() {
+        g1_constants(&mut constants, format!("IC{}", i).as_str(), ic);
+    }
+
+    let mut vk_x = String::new();
+    for i in 0..n {
+        writeln!(vk_x, "        vk = _add(vk, _mul(IC{}_X, IC{}_Y, input[{}]));", i + 1, i + 1, i).unwrap();
+    }
+
+    let pairing = [
+        "a[0]", "(Q - a[1] % Q) % Q", "b[0][0]", "b[0][1]", "b[1][0]", "b[1][1]",
+        "ALPHA_X", "ALPHA_Y", "BETA_X1", "BETA_X0", "BETA_Y1", "BETA_Y0",
+        "vk[0]", "vk[1]", "GAMMA_X1", "GAMMA_X0", "GAMMA_Y1", "GAMMA_Y0",
+        "c[0]", "c[1]", "DELTA_X1", "DELTA_X0", "DELTA_Y1", "DELTA_Y0",
+    ]
+    .iter()
+    .enumerate()
+    .map(|(i, v)| format!("        buf[{}] = {};\n", i, v))
+    .collect::<String>();
+
+    format!(
+        r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+// Groth16 verifier over BN254, generated by vdf_snark
+contract {name} {{
+    // scalar field and base field moduli
+    uint256 constant R = {r};
+    uint256 constant Q = {q};
+
+{constants}
+    function _add(uint256[2] memory p, uint256[2] memory q) internal view returns (uint256[2] memory s) {{
+        uint256[4] memory buf;
+        buf[0] = p[0];
+        buf[1] = p[1];
+        buf[2] = q[0];
+        buf[3] = q[1];
+        bool ok;
+        assembly {{
+            ok := staticcall(gas(), 0x06, buf, 0x80, s, 0x40)
+        }}
+        require(ok, "ecAdd failed");
+    }}
+
+    function _mul(uint256 x, uint256 y, uint256 k) internal view returns (uint256[2] memory s) {{
+        uint256[3] memory buf;
+        buf[0] = x;
+        buf[1] = y;
+        buf[2] = k;
+        bool ok;
+        assembly {{
+            ok := staticcall(gas(), 0x07, buf, 0x60, s, 0x40)
+        }}
+        require(ok, "ecMul failed");
+    }}
+
+    function verifyProof(
+        uint256[2] calldata a,
+        uint256[2][2] calldata b,
+        uint256[2] calldata c,
+        uint256[{n}] calldata input
+    ) external view returns (bool) {{
+        for (uint256 i = 0; i < {n}; i++) {{
+            if (input[i] >= R) {{
+                return false;
+            }}
+        }}
+
+        uint256[2] memory vk;
+        vk[0] = IC0_X;
+        vk[1] = IC0_Y;
+{vk_x}
+        uint256[24] memory buf;
+{pairing}
+        uint256[1] memory out;
+        bool ok;
+        assembly {{
+            ok := staticcall(gas(), 0x08, buf, 0x300, out, 0x20)
+        }}
+        return ok && out[0] == 1;
+    }}
+}}
+"#,
+        name = name,
+        r = BN254_R,
+        q = BN254_Q,
+        constants = constants,
+        n = n,
+        vk_x = vk_x,
+        pairing = pairing,
+    )
+}
@@ -0,0 +1,238 @@
+use rand::RngCore;
+use rug::integer::{IsPrime, Order};
+use rug::Integer;
+use sha2::{Digest, Sha256};
+
+use crate::timelock::random_element;
+use crate::wesolowski;
+use crate::{Delay, TrapdoorVDF};
+
+// k-of-n threshold trapdoor evaluation (Shoup, "Practical Threshold Signatures").
+//
+// With safe primes p = 2p'+1, q = 2q'+1 and m = p'q', the dealer shares
+// 'd = (D - b) * (4Δ^2)^-1 mod m' for the delay exponent D, its parity b = D mod 2 and Δ = n!
+// with a degree k-1 polynomial over Z_m. Operator i returns 'x_i = x^(2Δ s_i)' with a proof
+// of equality of discrete logs against its verification key 'v_i = v^s_i'. Any k valid
+// partials combine with integer Lagrange coefficients 'λ_j = Δ * L_j(0)' into
+// 'prod x_j^(2λ_j) = x^(4Δ^2 d)'. The exponents '4Δ^2 d' and 'D - b' agree mod m and are both
+// even, so by CRT they agree mod λ(N) = 2m, and multiplying by 'x^b' gives 'x^D', the VDF
+// output on x, for every unit x and not only squares. D is a product of factors reduced mod
+// N, so b is public but differs between moduli. Fewer than k operators learn nothing about d.
+
+// bits of the challenge in the partial evaluation proofs
+const CHALLENGE_BITS: u32 = 128;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ThresholdError {
+    MissingTrapdoor,
+    // Shoup's scheme needs p and q to be safe primes
+    NotSafePrimes,
+    // the threshold must satisfy 2 <= k <= n
+    InvalidThreshold,
+    NotEnoughShares,
+    InvalidPartial(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ThresholdParams {
+    pub modulus: Integer,
+    pub delay: Delay,
+    pub n: usize,
+    pub k: usize,
+    // Δ = n!
+    pub delta: Integer,
+    // D mod 2, restored by 'combine'
+    pub parity: u32,
+    // random square and the operators' verification keys v_i = v^s_i
+    pub v: Integer,
+    pub verification_keys: Vec<Integer>,
+}
+
+// share of operator 'index' (1-based)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyShare {
+    pub index: usize,
+    share: Integer,
+}
+
+// proof that 'log_v(v_i) = log_(x^4Δ)(x_i^2)'
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShareProof {
+    pub c: Integer,
+    pub z: Integer,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartialEval {
+    pub index: usize,
+    pub value: Integer,
+    pub proof: ShareProof,
+}
+
+fn random_bits(bits: u32) -> Integer {
+    let mut bytes = vec![0u8; ((bits + 7) / 8) as usize];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    Integer::from_digits(&bytes, Order::Msf).keep_bits(bits)
+}
+
+fn is_safe_prime(p: &Integer) -> bool {
+    let p_ = Integer::from(p - 1u32) >> 1u32;
+    p.is_probably_prime(30) != IsPrime::No && p_.is_probably_prime(30) != IsPrime::No
+}
+
+// deal shares of the trapdoor of 'vdf' for 'delay' to n operators with threshold k
+pub fn deal(vdf: &TrapdoorVDF, delay: &Delay, n: usize, k: usize) -> Result<(ThresholdParams, Vec<KeyShare>), ThresholdError> {
+    if k < 2 || k > n {
+        return Err(ThresholdError::InvalidThreshold);
+    }
+    let (p, q) = vdf.factors.as_ref().ok_or(ThresholdError::MissingTrapdoor)?;
+    if !is_safe_prime(p) || !is_safe_prime(q) {
+        return Err(ThresholdError::NotSafePrimes);
+    }
+    let n_ = &vdf.group.m;
+    let m = Integer::from(p - 1u32) / 2u32 * (Integer::from(q - 1u32) / 2u32);
+    let delta = Integer::from(Integer::factorial(n as u32));
+
+    // d = (D - b) * (4Δ^2)^-1 mod m
+    let xs = vdf.exponent(delay);
+    let parity = wesolowski::exponent_mod(&xs, &Integer::from(2u32)).to_u32().unwrap();
+    let d_exp = wesolowski::exponent_mod(&xs, &m) - parity;
+    let inv = (Integer::from(delta.square_ref()) * 4u32).invert(&m).unwrap();
+    let d = (d_exp * inv).rem_euc(&m);
+
+    let mut coeffs = vec![d];
+    coeffs.extend((1..k).map(|_| random_bits(m.significant_bits() + 128) % &m));
+
+    let v = random_element(n_).pow_mod(&Integer::from(2u32), n_).unwrap();
+    let mut shares = Vec::with_capacity(n);
+    let mut verification_keys = Vec::with_capacity(n);
+    for i in 1..=n {
+        let share = coeffs
+            .iter()
+            .rev()
+            .fold(Integer::new(), |acc, a| (acc * i as u32 + a) % &m);
+        verification_keys.push(v.clone().pow_mod(&share, n_).unwrap());
+        shares.push(KeyShare {
+            index: i,
+            share: share,
+        });
+    }
+
+    Ok((
+        ThresholdParams {
+            modulus: n_.clone(),
+            delay: delay.clone(),
+            n: n,
+            k: k,
+            delta: delta,
+            parity: parity,
+            v: v,
+            verification_keys: verification_keys,
+        },
+        shares,
+    ))
+}
+
+impl ThresholdParams {
+
+    fn challenge(&self, values: [&Integer; 6]) -> Integer {
+        let mut hasher = Sha256::new();
+        wesolowski::absorb(&mut hasher, &self.modulus);
+        for v in values {
+            wesolowski::absorb(&mut hasher, v);
+        }
+        let digest = hasher.finalize();
+        Integer::from_digits(&digest[..(CHALLENGE_BITS / 8) as usize], Order::Msf)
+    }
+
+    fn power(&self, b: &Integer, e: &Integer) -> Integer {
+        b.clone().pow_mod(e, &self.modulus).unwrap()
+    }
+
+    // x^(4Δ)
+    fn x_tilde(&self, x: &Integer) -> Integer {
+        self.power(x, &(Integer::from(&self.delta * 4u32)))
+    }
+
+    pub fn verify_partial(&self, x: &Integer, partial: &PartialEval) -> bool {
+        if partial.index == 0 || partial.index > self.n {
+            return false;
+        }
+        let v_i = &self.verification_keys[partial.index - 1];
+        let x_t = self.x_tilde(x);
+        let x_i2 = self.power(&partial.value, &Integer::from(2u32));
+        let ShareProof { c, z } = &partial.proof;
+
+        // v' = v^z * v_i^-c, x' = x~^z * x_i^-2c
+        let neg_c = Integer::from(-c);
+        let v_ = self.power(&self.v, z) * self.power(v_i, &neg_c) % &self.modulus;
+        let x_ = self.power(&x_t, z) * self.power(&x_i2, &neg_c) % &self.modulus;
+        *c == self.challenge([&self.v, &x_t, v_i, &x_i2, &v_, &x_])
+    }
+
+    // integer Lagrange coefficient Δ * L_j(0) over the indices 'set'
+    fn lagrange(&self, set: &[usize], j: usize) -> Integer {
+        let mut num = self.delta.clone();
+        let mut den = Integer::from(1u32);
+        for &j_ in set {
+            if j_ != j {
+                num *= -(j_ as i64);
+                den *= j as i64 - j_ as i64;
+            }
+        }
+        num / den
+    }
+
+    // combine k valid partial evaluations into 'x^D % N', the same as 'eval_delay'
+    pub fn combine(&self, x: &Integer, partials: &[PartialEval]) -> Result<Integer, ThresholdError> {
+        let mut chosen: Vec<&PartialEval> = Vec::with_capacity(self.k);
+        for partial in partials {
+            if chosen.iter().any(|c| c.index == partial.index) {
+                continue;
+            }
+            if !self.verify_partial(x, partial) {
+                return Err(ThresholdError::InvalidPartial(partial.index));
+            }
+            chosen.push(partial);
+            if chosen.len() == self.k {
+                break;
+            }
+        }
+        if chosen.len() < self.k {
+            return Err(ThresholdError::NotEnoughShares);
+        }
+
+        let set: Vec<usize> = chosen.iter().map(|c| c.index).collect();
+        let mut w = self.power(x, &Integer::from(self.parity));
+        for c in chosen {
+            let e = self.lagrange(&set, c.index) * 2u32;
+            w = w * self.power(&c.value, &e) % &self.modulus;
+        }
+        Ok(w)
+    }
+}
+
+impl KeyShare {
+
+    // x_i = x^(2Δ s_i) with its proof of correctness
+    pub fn partial_eval(&self, params: &ThresholdParams, x: &Integer) -> PartialEval {
+        let value = params.power(x, &(Integer::from(&params.delta * 2u32) * &self.share));
+
+        let x_t = params.x_tilde(x);
+        let x_i2 = params.power(&value, &Integer::from(2u32));
+        let r = random_bits(params.modulus.significant_bits() + 2 * CHALLENGE_BITS);
+        let v_ = params.power(&params.v, &r);
+        let x_ = params.power(&x_t, &r);
+        let c = params.challenge([&params.v, &x_t, &params.verification_keys[self.index - 1], &x_i2, &v_, &x_]);
+        let z = Integer::from(&self.share * &c) + r;
+
+        PartialEval {
+            index: self.index,
+            value: value,
+            proof: ShareProof {
+                c: c,
+                z: z,
+            },
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use bls12_381::{G1Affine, G1Projective, Scalar};
+use ff::Field;
+use rug::integer::Order;
+use rug::Integer;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::homomorphic::{Puzzle, PuzzleParams};
+use crate::timelock::{self, TimelockError};
+
+// Verifiable timed Schnorr signatures over the prime-order group G1 of BLS12-381
+// (Thyagarajan et al., CCS 2020).
+//
+// The 's' part of a signature '(R, s)' is Shamir-shared with threshold t among n shares,
+// every share is locked in a Malavolta-Thyagarajan puzzle of public 'PuzzleParams', and the
+// shares are committed to as 'S_i = g^f(i)'. Fiat-Shamir picks t-1 puzzles that the signer
+// opens by revealing their randomness, so a verifier recomputes each opened puzzle instead of
+// trusting a VDF output, which whoever knows φ(N) could prove for any value. A verifier
+// checks the opened shares and that all commitments lie on one degree t-1 polynomial through
+// 'g^s = R * pk^c', so solving any unopened puzzle yields the signature.
+//
+// The signer needs no trapdoor. The parameters must come from the receiver or another party
+// the signer does not control: with φ(N) the signer could publish an 'h' other than 'g^e', and
+// the unopened puzzles would then not solve to the shares. A cheating signer escapes with probability 1/C(n, t-1), and since the choice is
+// non-interactive it can retry offline, so C(n, t-1) must be at least 2^SECURITY_BITS,
+// e.g. n = 136 and t = 68.
+
+const DOMAIN: &[u8] = b"vdf_snark/timed_signature";
+
+pub const SECURITY_BITS: f64 = 128.0;
+
+#[derive(Clone)]
+pub struct SigningKey {
+    sk: Scalar,
+    pub pk: G1Projective,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Signature {
+    pub r: G1Projective,
+    pub s: Scalar,
+}
+
+// one of the t-1 opened puzzles, with its share and the randomness it was generated with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpenedShare {
+    pub index: usize,
+    pub share: Scalar,
+    pub randomness: Integer,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimedSignature {
+    pub r: G1Projective,
+    // S_i = g^f(i) for i = 1..=n
+    pub commitments: Vec<G1Projective>,
+    pub puzzles: Vec<Puzzle>,
+    pub opened: Vec<OpenedShare>,
+}
+
+impl SigningKey {
+    pub fn generate() -> Self {
+        Self::generate_with_rng(rand::thread_rng())
+    }
+
+    pub fn generate_with_rng<R: rand::RngCore>(rng: R) -> Self {
+        let sk = Scalar::random(rng);
+        Self {
+            sk: sk,
+            pk: G1Projective::generator() * sk,
+        }
+    }
+
+    pub fn sign(&self, msg: &[u8]) -> Signature {
+        let k = Scalar::random(rand::thread_rng());
+        let r = G1Projective::generator() * k;
+        Signature {
+            r: r,
+            s: k + challenge(&r, &self.pk, msg) * self.sk,
+        }
+    }
+}
+
+fn compressed(p: &G1Projective) -> [u8; 48] {
+    G1Affine::from(p).to_compressed()
+}
+
+// c = H(R, pk, msg)
+fn challenge(r: &G1Projective, pk: &G1Projective, msg: &[u8]) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(DOMAIN);
+    hasher.update(compressed(r));
+    hasher.update(compressed(pk));
+    hasher.update(msg);
+    let mut wide = [0u8; 64];
+    wide.copy_from_slice(&hasher.finalize());
+    Scalar::from_bytes_wide(&wide)
+}
+
+pub fn verify_signature(pk: &G1Projective, msg: &[u8], sig: &Signature) -> bool {
+    G1Projective::generator() * sig.s == sig.r + pk * challenge(&sig.r, pk, msg)
+}
+
+// Lagrange coefficient of 'xs[k]' evaluated at 'x'
+fn lagrange(xs: &[u64], k: usize, x: u64) -> Scalar {
+    let mut num = Scalar::one();
+    let mut den = Scalar::one();
+    for (m, &x_m) in xs.iter().enumerate() {
+        if m != k {
+            num *= Scalar::from(x) - Scalar::from(x_m);
+            den *= Scalar::from(xs[k]) - Scalar::from(x_m);
+        }
+    }
+    num * den.invert().unwrap()
+}
+
+// log2 of the binomial coefficient C(n, k)
+fn log2_binomial(n: usize, k: usize) -> f64 {
+    (0..k).map(|i| ((n - i) as f64 / (i + 1) as f64).log2()).sum()
+}
+
+fn check_threshold(n: usize, t: usize) -> bool {
+    t >= 2 && t <= n && log2_binomial(n, t - 1) >= SECURITY_BITS
+}
+
+// Fiat-Shamir choice of the t-1 puzzles to open (0-based): indices are read as 64-bit
+// words of 'SHA-256(transcript || counter)', rejection-sampled to be uniform below n
+fn opened_indices(params: &PuzzleParams, pk: &G1Projective, msg: &[u8], r: &G1Projective, commitments: &[G1Projective], puzzles: &[Puzzle], t: usize) -> Vec<usize> {
+    let mut hasher = Sha256::new();
+    hasher.update(DOMAIN);
+    hasher.update(timelock::header(&params.modulus, &params.g, &params.delay));
+    hasher.update(params.h.to_digits::<u8>(Order::Msf));
+    hasher.update(compressed(pk));
+    hasher.update(msg);
+    hasher.update(compressed(r));
+    for c in commitments {
+        hasher.update(compressed(c));
+    }
+    for p in puzzles {
+        for v in [&p.u, &p.v] {
+            let bytes = v.to_digits::<u8>(Order::Msf);
+            hasher.update((bytes.len() as u64).to_be_bytes());
+            hasher.update(&bytes);
+        }
+    }
+
+    let seed = hasher.finalize();
+
+    let n = commitments.len() as u64;
+    let bound = u64::MAX - u64::MAX % n;
+    let mut idx = Vec::with_capacity(t - 1);
+    let mut counter = 0u64;
+    while idx.len() < t - 1 {
+        let block = Sha256::new()
+            .chain_update(seed)
+            .chain_update(counter.to_be_bytes())
+            .finalize();
+        counter += 1;
+        for word in block.chunks(8) {
+            let v = u64::from_be_bytes(word.try_into().unwrap());
+            let i = (v % n) as usize;
+            if v < bound && idx.len() < t - 1 && !idx.contains(&i) {
+                idx.push(i);
+            }
+        }
+    }
+    idx.sort_unstable();
+    idx
+}
+
+fn encode_share(share: &Scalar) -> Integer {
+    Integer::from_digits(&share.to_bytes(), Order::Lsf)
+}
+
+fn decode_share(s: &Integer) -> Option<Scalar> {
+    if s.significant_bits() > 256 {
+        return None;
+    }
+    let mut bytes = [0u8; 32];
+    s.write_digits(&mut bytes, Order::Lsf);
+    let share = Scalar::from_bytes(&bytes);
+    if bool::from(share.is_some()) {
+        Some(share.unwrap())
+    } else {
+        None
+    }
+}
+
+// sign 'msg' and lock the signature for the delay of 'params' in n puzzles with threshold t
+pub fn lock(params: &PuzzleParams, key: &SigningKey, msg: &[u8], n: usize, t: usize) -> Result<TimedSignature, TimelockError> {
+    // shares are locked as they are, so must stay below N
+    if !check_threshold(n, t) || params.modulus.significant_bits() <= 256 {
+        return Err(TimelockError::WeakParameters);
+    }
+    let sig = key.sign(msg);
+
+    // f(0) = s
+    let mut coeffs = vec![sig.s];
+    coeffs.extend((1..t).map(|_| Scalar::random(rand::thread_rng())));
+    let shares: Vec<Scalar> = (1..=n as u64)
+        .map(|i| coeffs.iter().rev().fold(Scalar::zero(), |acc, a| acc * Scalar::from(i) + a))
+        .collect();
+
+    let commitments: Vec<G1Projective> = shares.iter().map(|f| G1Projective::generator() * f).collect();
+    let n2 = Integer::from(params.modulus.square_ref());
+    let randomness: Vec<Integer> = shares.iter().map(|_| timelock::random_element(&n2)).collect();
+    let puzzles: Vec<Puzzle> = shares
+        .iter()
+        .zip(&randomness)
+        .map(|(f, r)| params.generate_with(&encode_share(f), r))
+        .collect();
+
+    let opened = opened_indices(params, &key.pk, msg, &sig.r, &commitments, &puzzles, t)
+        .into_iter()
+        .map(|i| OpenedShare {
+            index: i,
+            share: shares[i],
+            randomness: randomness[i].clone(),
+        })
+        .collect();
+
+    Ok(TimedSignature {
+        r: sig.r,
+        commitments: commitments,
+        puzzles: puzzles,
+        opened: opened,
+    })
+}
+
+impl TimedSignature {
+
+    fn threshold(&self) -> usize {
+        self.opened.len() + 1
+    }
+
+    // check that solving any unopened puzzle of 'params' yields a valid signature on 'msg'
+    // under 'pk'
+    pub fn verify(&self, params: &PuzzleParams, pk: &G1Projective, msg: &[u8]) -> bool {
+        let n = self.commitments.len();
+        let t = self.threshold();
+        if self.puzzles.len() != n || !check_threshold(n, t) {
+            return false;
+        }
+
+        // the opened puzzles are the Fiat-Shamir choice, and regenerating them from the
+        // revealed randomness gives the same puzzles over the committed shares
+        let idx = opened_indices(params, pk, msg, &self.r, &self.commitments, &self.puzzles, t);
+        if idx != self.opened.iter().map(|o| o.index).collect::<Vec<_>>() {
+            return false;
+        }
+        for o in &self.opened {
+            if params.generate_with(&encode_share(&o.share), &o.randomness) != self.puzzles[o.index]
+                || G1Projective::generator() * o.share != self.commitments[o.index]
+            {
+                return false;
+            }
+        }
+
+        // interpolate through g^s at 0 and the opened commitments; every other
+        // commitment must lie on the same polynomial
+        let g_s = self.r + pk * challenge(&self.r, pk, msg);
+        let mut xs = vec![0u64];
+        let mut points = vec![g_s];
+        for o in &self.opened {
+            xs.push(o.index as u64 + 1);
+            points.push(self.commitments[o.index]);
+        }
+        (0..n).filter(|j| !idx.contains(j)).all(|j| {
+            let x = j as u64 + 1;
+            let expected = points
+                .iter()
+                .enumerate()
+                .fold(G1Projective::identity(), |acc, (k, p)| acc + p * lagrange(&xs, k, x));
+            expected == self.commitments[j]
+        })
+    }
+
+    // the signature from the opened shares and the share in unopened puzzle j
+    fn force_one(&self, params: &PuzzleParams, j: usize) -> Result<Signature, TimelockError> {
+        let share = params
+            .solve(&self.puzzles[j])
+            .and_then(|s| decode_share(&s))
+            .ok_or(TimelockError::Decrypt)?;
+        if G1Projective::generator() * share != self.commitments[j] {
+            return Err(TimelockError::InvalidProof);
+        }
+
+        let mut xs: Vec<u64> = self.opened.iter().map(|o| o.index as u64 + 1).collect();
+        let mut ys: Vec<Scalar> = self.opened.iter().map(|o| o.share).collect();
+        xs.push(j as u64 + 1);
+        ys.push(share);
+        let s = ys
+            .iter()
+            .enumerate()
+            .fold(Scalar::zero(), |acc, (k, y)| acc + y * lagrange(&xs, k, 0));
+
+        Ok(Signature {
+            r: self.r,
+            s: s,
+        })
+    }
+
+    // recover the signature on 'msg' by solving unopened puzzles sequentially, one after
+    // another, until one of them yields a valid signature
+    pub fn force(&self, params: &PuzzleParams, pk: &G1Projective, msg: &[u8]) -> Result<Signature, TimelockError> {
+        let opened: Vec<usize> = self.opened.iter().map(|o| o.index).collect();
+        (0..self.puzzles.len())
+            .filter(|j| !opened.contains(j))
+            .filter_map(|j| self.force_one(params, j).ok())
+            .find(|sig| verify_signature(pk, msg, sig))
+            .ok_or(TimelockError::InvalidProof)
+    }
+}
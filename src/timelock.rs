@@ -0,0 +1,131 @@
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use rand::RngCore;
+use rug::integer::Order;
+use rug::Integer;
+use sha2::Sha256;
+
+use crate::params::ParamsError;
+use crate::{Delay, TrapdoorVDF};
+
+const KDF_SALT: &[u8] = b"vdf_snark/timelock";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TimelockError {
+    // sealing needs p and q or φ(N)
+    MissingTrapdoor,
+    // wrong key, or the puzzle was tampered with
+    Decrypt,
+    // a proof attached to a puzzle or commitment does not verify
+    InvalidProof,
+    // the modulus or a value does not fit the circuit limb layout
+    Params(ParamsError),
+    // cut-and-choose parameters below the required soundness
+    WeakParameters,
+}
+
+// Rivest-Shamir-Wagner puzzle: the key is derived from 'base^e % modulus' for the delay exponent 'e'
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Puzzle {
+    pub modulus: Integer,
+    pub base: Integer,
+    pub delay: Delay,
+    pub nonce: [u8; 12],
+    pub ciphertext: Vec<u8>,
+}
+
+impl Puzzle {
+    // header bound to the ciphertext as associated data
+    fn aad(&self) -> Vec<u8> {
+        header(&self.modulus, &self.base, &self.delay)
+    }
+}
+
+pub(crate) fn header(m: &Integer, x: &Integer, delay: &Delay) -> Vec<u8> {
+    let mut aad = Vec::new();
+    for v in [m, x, delay.time()] {
+        let bytes = v.to_digits::<u8>(Order::Msf);
+        aad.extend_from_slice(&(bytes.len() as u64).to_be_bytes());
+        aad.extend_from_slice(&bytes);
+    }
+    aad
+}
+
+// uniform element of Z_m^* other than 1
+pub(crate) fn random_element(m: &Integer) -> Integer {
+    random_element_with(m, &mut rand::thread_rng())
+}
+
+pub(crate) fn random_element_with<R: RngCore>(m: &Integer, rng: &mut R) -> Integer {
+    let mut bytes = vec![0u8; (m.significant_bits() / 8 + 16) as usize];
+    loop {
+        rng.fill_bytes(&mut bytes);
+        let x = Integer::from_digits(&bytes, Order::Msf) % m;
+        if x > 1 && Integer::from(x.gcd_ref(m)) == 1 {
+            return x;
+        }
+    }
+}
+
+// HKDF-SHA256 of the fixed-length encoding of 'y' in Z_m
+pub(crate) fn derive_key(m: &Integer, y: &Integer, info: &[u8]) -> [u8; 32] {
+    let len = ((m.significant_bits() + 7) / 8) as usize;
+    let mut ikm = vec![0u8; len];
+    y.write_digits(&mut ikm, Order::Msf);
+
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(KDF_SALT), &ikm)
+        .expand(info, &mut key)
+        .unwrap();
+    key
+}
+
+pub(crate) fn encrypt(key: &[u8; 32], nonce: &[u8; 12], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(nonce), Payload { msg: plaintext, aad: aad })
+        .unwrap()
+}
+
+pub(crate) fn decrypt(key: &[u8; 32], nonce: &[u8; 12], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, TimelockError> {
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: aad })
+        .map_err(|_| TimelockError::Decrypt)
+}
+
+// lock 'plaintext' for 'delay': the key is computed quickly with the trapdoor of 'params'
+pub fn seal(params: &TrapdoorVDF, delay: &Delay, plaintext: &[u8]) -> Result<Puzzle, TimelockError> {
+    if !params.has_trapdoor() {
+        return Err(TimelockError::MissingTrapdoor);
+    }
+    let m = &params.group.m;
+    let x = random_element(m);
+    let y = params.eval_delay_with_trapdoor(&x, delay);
+
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let aad = header(m, &x, delay);
+    let key = derive_key(m, &y, &aad);
+    Ok(Puzzle {
+        modulus: m.clone(),
+        base: x,
+        delay: delay.clone(),
+        nonce: nonce,
+        ciphertext: encrypt(&key, &nonce, &aad, plaintext),
+    })
+}
+
+// recover the plaintext by evaluating the delay sequentially
+pub fn open(puzzle: &Puzzle) -> Result<Vec<u8>, TimelockError> {
+    let vdf = TrapdoorVDF::setup("2", puzzle.modulus.to_string_radix(10).as_str());
+    let y = vdf.eval_delay(&puzzle.base, &puzzle.delay);
+    open_with_output(puzzle, &y)
+}
+
+// recover the plaintext from an already computed 'base^e % modulus'
+pub fn open_with_output(puzzle: &Puzzle, y: &Integer) -> Result<Vec<u8>, TimelockError> {
+    let aad = puzzle.aad();
+    let key = derive_key(&puzzle.modulus, y, &aad);
+    decrypt(&key, &puzzle.nonce, &aad, &puzzle.ciphertext)
+}
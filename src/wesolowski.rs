@@ -0,0 +1,361 @@
+use bellman_bignat::group::{RsaGroup, SemiGroup};
+use rand::Rng;
+use rug::integer::Order;
+use rug::Integer;
+use sha2::{Digest, Sha256};
+
+use crate::batch::multi_power;
+use crate::{Delay, TrapdoorVDF};
+
+// bits of the Fiat-Shamir challenge prime 'l'
+pub const CHALLENGE_BITS: u32 = 128;
+// bits of the random coefficients combining the checks in 'verify_batch'
+pub const BATCH_BITS: u32 = 64;
+
+// Wesolowski proof that 'output = x^e % m', with 'pi = x^floor(e/l) % m'
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Proof {
+    pub output: Integer,
+    pub pi: Integer,
+}
+
+// length-prefixed big-endian bytes of 'v'
+pub(crate) fn absorb(hasher: &mut Sha256, v: &Integer) {
+    let bytes = v.to_digits::<u8>(Order::Msf);
+    hasher.update((bytes.len() as u64).to_be_bytes());
+    hasher.update(&bytes);
+}
+
+// hash (m, x, y) to a CHALLENGE_BITS prime
+pub fn challenge(g: &RsaGroup, x: &Integer, y: &Integer) -> Integer {
+    let mut hasher = Sha256::new();
+    for v in [&g.m, x, y] {
+        absorb(&mut hasher, v);
+    }
+    let digest = hasher.finalize();
+
+    let mut l = Integer::from_digits(&digest[..(CHALLENGE_BITS / 8) as usize], Order::Msf);
+    l.set_bit(CHALLENGE_BITS - 1, true);
+    l.next_prime()
+}
+
+// compute 'prod(xs)'
+pub(crate) fn exponent(xs: &[Integer]) -> Integer {
+    let mut acc = Integer::from(1usize);
+    for x in xs {
+        acc *= x;
+    }
+    acc
+}
+
+// compute 'prod(xs) % m'
+pub(crate) fn exponent_mod(xs: &[Integer], m: &Integer) -> Integer {
+    let mut acc = Integer::from(1usize);
+    for x in xs {
+        acc *= x;
+        acc %= m;
+    }
+    acc
+}
+
+// compute 'floor(prod(xs)/l) % m' without building prod(xs):
+// with e = l*q + r, 'e % (l*m) = l*(q % m) + r'
+pub(crate) fn quotient_mod(xs: &[Integer], l: &Integer, m: &Integer) -> Integer {
+    let lm = Integer::from(l * m);
+    let e = exponent_mod(xs, &lm);
+    let r = Integer::from(&e % l);
+    (e - r) / l
+}
+
+// check 'pi^l * x^r == output' where 'r = prod(xs) % l'
+pub fn verify(g: &RsaGroup, x: &Integer, xs: &[Integer], proof: &Proof) -> bool {
+    let l = challenge(g, x, &proof.output);
+    let r = exponent_mod(xs, &l);
+    let lhs = g.op(&g.power(&proof.pi, &l), &g.power(x, &r));
+    lhs == proof.output
+}
+
+// 'verify' in the quotient group by {1, -1}: the output must be canonical, and 'pi^l * x^r'
+// may be it or its negation. Unlike 'verify', the prover then has no choice of sign.
+pub fn verify_quotient(g: &RsaGroup, x: &Integer, xs: &[Integer], proof: &Proof) -> bool {
+    if canonical(g, &proof.output) != proof.output {
+        return false;
+    }
+    let l = challenge(g, x, &proof.output);
+    let r = exponent_mod(xs, &l);
+    let lhs = g.op(&g.power(&proof.pi, &l), &g.power(x, &r));
+    canonical(g, &lhs) == proof.output
+}
+
+// the representative 'min(v, m - v)' of '±v' in the quotient group by {1, -1}
+pub fn canonical(g: &RsaGroup, v: &Integer) -> Integer {
+    let v = Integer::from(v % &g.m);
+    let neg = Integer::from(&g.m - &v);
+    if neg < v {
+        neg
+    } else {
+        v
+    }
+}
+
+// indices of the (input, proof) pairs that failed 'verify_batch'
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchError {
+    pub invalid: Vec<usize>,
+}
+
+// verify many (x, proof) pairs for the same modulus and exponent 'prod(xs)'.
+// The checks 'pi^l * x^r == y' are combined with random coefficients rho into
+// 'prod((pi^l * x^r)^rho) == prod(y^rho)', and both sides are computed with one shared
+// squaring chain. A failing batch is bisected down to the invalid pairs, with the same rho.
+//
+// In the RSA group, -1 has order 2 and a wrong sign would pass whenever its rho is even, so
+// the combined check is done in the quotient group by {1, -1} ('RsaQuotientGroup'), where y
+// and -y are one class. A canonical output, as made by 'TrapdoorVDF::prove_batch_quotient',
+// is then pinned to 'canonical(x^prod(xs))'. Any other output, e.g. half of those from
+// 'prove' or 'prove_batch_with_trapdoor', is also checked on its own with 'verify', so it is
+// accepted only when it is exactly 'x^prod(xs)'.
+pub fn verify_batch(g: &RsaGroup, xs: &[Integer], items: &[(Integer, Proof)]) -> Result<(), BatchError> {
+    let mut rng = rand::thread_rng();
+    let ls: Vec<Integer> = items
+        .iter()
+        .map(|(x, proof)| challenge(g, x, &proof.output))
+        .collect();
+    let rs: Vec<Integer> = ls.iter().map(|l| exponent_mod(xs, l)).collect();
+    let rhos: Vec<Integer> = items
+        .iter()
+        .map(|_| Integer::from(rng.gen_range(1..=u64::MAX)))
+        .collect();
+
+    let idx: Vec<usize> = (0..items.len()).collect();
+    let mut invalid = Vec::new();
+    bisect(g, items, &ls, &rs, &rhos, &idx, &mut invalid);
+
+    // the sign of a non-canonical output is only fixed by its single check
+    for i in idx {
+        let (x, proof) = &items[i];
+        if !invalid.contains(&i) && canonical(g, &proof.output) != proof.output && !verify(g, x, xs, proof) {
+            invalid.push(i);
+        }
+    }
+
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        invalid.sort_unstable();
+        Err(BatchError {
+            invalid: invalid,
+        })
+    }
+}
+
+fn bisect(g: &RsaGroup, items: &[(Integer, Proof)], ls: &[Integer], rs: &[Integer], rhos: &[Integer], idx: &[usize], invalid: &mut Vec<usize>) {
+    if idx.is_empty() || combined_check(g, items, ls, rs, rhos, idx) {
+        return;
+    }
+    if idx.len() == 1 {
+        invalid.push(idx[0]);
+        return;
+    }
+    let (lo, hi) = idx.split_at(idx.len() / 2);
+    bisect(g, items, ls, rs, rhos, lo, invalid);
+    bisect(g, items, ls, rs, rhos, hi, invalid);
+}
+
+// both sides of the combined check, compared up to sign
+fn combined_check(g: &RsaGroup, items: &[(Integer, Proof)], ls: &[Integer], rs: &[Integer], rhos: &[Integer], idx: &[usize]) -> bool {
+    let mut bases = Vec::with_capacity(2 * idx.len());
+    let mut exps = Vec::with_capacity(2 * idx.len());
+    let mut ys = Vec::with_capacity(idx.len());
+    let mut ys_exps = Vec::with_capacity(idx.len());
+    for &i in idx {
+        let (x, proof) = &items[i];
+        bases.push(proof.pi.clone());
+        exps.push(Integer::from(&rhos[i] * &ls[i]));
+        bases.push(x.clone());
+        exps.push(Integer::from(&rhos[i] * &rs[i]));
+        ys.push(proof.output.clone());
+        ys_exps.push(rhos[i].clone());
+    }
+
+    canonical(g, &multi_power(g, &bases, &exps)) == canonical(g, &multi_power(g, &ys, &ys_exps))
+}
+
+// memory/time trade-off of 'CheckpointProver': a checkpoint 'x^(2^i)' is kept every 'k * gamma'
+// squarings, and the proof is assembled from the k-bit digits of 'floor(e/l)'
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CheckpointParams {
+    pub k: u32,
+    pub gamma: u32,
+}
+
+impl CheckpointParams {
+    const MAX_K: u32 = 16;
+
+    // choose (k, gamma) for a 'bits'-bit exponent with at most 'max_checkpoints' stored elements.
+    // Following Wesolowski §4.1, the proof costs about 'bits/k + gamma * 2^(k+1)' group
+    // operations after the evaluation, which is smallest near 'k = ln(x) - ln(ln(x))' for
+    // 'x = bits * ln(2) / (2 * gamma)'.
+    pub fn for_bits(bits: u32, max_checkpoints: usize) -> Self {
+        let t = bits.max(1) as f64;
+        let mut k = 1u32;
+        let mut gamma = 1u32;
+        for _ in 0..2 {
+            let x = (t * std::f64::consts::LN_2 / (2.0 * gamma as f64)).max(3.0);
+            k = ((x.ln() - x.ln().ln() + 0.25).round() as u32).clamp(1, Self::MAX_K);
+            gamma = ((t / (k as f64 * max_checkpoints.max(1) as f64)).ceil() as u32).max(1);
+        }
+        Self {
+            k: k,
+            gamma: gamma,
+        }
+    }
+}
+
+// evaluates 'x^e % m' while keeping checkpoints, so the Wesolowski proof can be computed
+// afterwards in about 'bits(e)/k' group operations instead of a second full pass
+pub struct CheckpointProver<'a> {
+    group: &'a RsaGroup,
+    params: CheckpointParams,
+    base: Integer,
+    exponent: &'a Integer,
+    // x^(2^(i * k * gamma)) for i = 0, 1, ..
+    checkpoints: Vec<Integer>,
+    output: Integer,
+}
+
+impl<'a> CheckpointProver<'a> {
+
+    // right-to-left exponentiation: the running power goes through every 'x^(2^i)'
+    pub fn eval(group: &'a RsaGroup, x: &Integer, e: &'a Integer, params: CheckpointParams) -> Self {
+        Self::eval_multi(group, std::slice::from_ref(x), e, params).pop().unwrap()
+    }
+
+    // 'eval' for every base of 'xs' in one pass over the shared exponent, with the
+    // independent squarings of the bases interleaved
+    pub fn eval_multi(group: &'a RsaGroup, xs: &[Integer], e: &'a Integer, params: CheckpointParams) -> Vec<Self> {
+        let m = &group.m;
+        let stride = params.k * params.gamma;
+
+        let mut pows: Vec<Integer> = xs.iter().map(|x| Integer::from(x % m)).collect();
+        let mut accs = vec![Integer::from(1usize); xs.len()];
+        let mut checkpoints = vec![Vec::new(); xs.len()];
+        for i in 0..e.significant_bits() {
+            let bit = e.get_bit(i);
+            for ((pow, acc), cps) in pows.iter_mut().zip(accs.iter_mut()).zip(checkpoints.iter_mut()) {
+                if i % stride == 0 {
+                    cps.push(pow.clone());
+                }
+                if bit {
+                    *acc *= &*pow;
+                    *acc %= m;
+                }
+                pow.square_mut();
+                *pow %= m;
+            }
+        }
+
+        xs.iter()
+            .zip(accs)
+            .zip(checkpoints)
+            .map(|((x, acc), cps)| Self {
+                group: group,
+                params: params,
+                base: x.clone(),
+                exponent: e,
+                checkpoints: cps,
+                output: acc,
+            })
+            .collect()
+    }
+
+    pub fn output(&self) -> &Integer {
+        &self.output
+    }
+
+    pub fn prove(&self) -> Proof {
+        let l = challenge(self.group, &self.base, &self.output);
+        let q = Integer::from(self.exponent / &l);
+
+        Proof {
+            output: self.output.clone(),
+            pi: self.power_from_checkpoints(&q),
+        }
+    }
+
+    // 'prove' for the canonical output, as checked by 'verify_quotient'
+    pub fn prove_quotient(&self) -> Proof {
+        let output = canonical(self.group, &self.output);
+        let l = challenge(self.group, &self.base, &output);
+        let q = Integer::from(self.exponent / &l);
+
+        Proof {
+            output: output,
+            pi: self.power_from_checkpoints(&q),
+        }
+    }
+
+    // k-bit digit of 'q' starting at bit 'pos'
+    fn digit(q: &Integer, pos: u32, k: u32) -> usize {
+        (0..k).filter(|t| q.get_bit(pos + t)).map(|t| 1usize << t).sum()
+    }
+
+    // compute 'x^q' as the product of 'C_b^(d_(b*gamma+j) * 2^(k*j))' over checkpoints C_b,
+    // gathering the checkpoints by digit value, then raising the buckets to their digit with
+    // the digit split in two halves (Wesolowski §4.1)
+    fn power_from_checkpoints(&self, q: &Integer) -> Integer {
+        let m = &self.group.m;
+        let CheckpointParams { k, gamma } = self.params;
+        let k1 = k / 2;
+        let k0 = k - k1;
+
+        let mut x = Integer::from(1usize);
+        for j in (0..gamma).rev() {
+            for _ in 0..k {
+                x.square_mut();
+                x %= m;
+            }
+
+            let mut ys = vec![Integer::from(1usize); 1 << k];
+            for (b, c) in self.checkpoints.iter().enumerate() {
+                let d = Self::digit(q, (b as u32 * gamma + j) * k, k);
+                if d != 0 {
+                    ys[d] *= c;
+                    ys[d] %= m;
+                }
+            }
+
+            for b1 in 0..(1usize << k1) {
+                let mut z = Integer::from(1usize);
+                for b0 in 0..(1usize << k0) {
+                    z *= &ys[(b1 << k0) | b0];
+                    z %= m;
+                }
+                x *= self.group.power(&z, &Integer::from(b1 << k0));
+                x %= m;
+            }
+            for b0 in 0..(1usize << k0) {
+                let mut z = Integer::from(1usize);
+                for b1 in 0..(1usize << k1) {
+                    z *= &ys[(b1 << k0) | b0];
+                    z %= m;
+                }
+                x *= self.group.power(&z, &Integer::from(b0));
+                x %= m;
+            }
+        }
+        x
+    }
+}
+
+impl TrapdoorVDF {
+
+    // evaluate without the trapdoor and prove from checkpoints, keeping at most
+    // 'max_checkpoints' group elements in memory
+    pub fn prove_with_checkpoints(&self, base: &Integer, delay: &Delay, max_checkpoints: usize) -> Proof {
+        let e = exponent(&self.exponent(delay));
+        let params = CheckpointParams::for_bits(e.significant_bits(), max_checkpoints);
+
+        CheckpointProver::eval(&self.group, base, &e, params).prove()
+    }
+}
@@ -0,0 +1,55 @@
+extern crate vdf_snark;
+
+use bellman_bignat::hash::hashes::Poseidon;
+use sapling_crypto::bellman::pairing::bn256::Bn256;
+use rug::Integer;
+use vdf_snark::beacon::{verify_chain, Beacon, OutputDigest, PoseidonDigest, Sha256Digest};
+use vdf_snark::wesolowski::{self, Proof};
+use vdf_snark::{Delay, TrapdoorVDF, RSA_2048};
+
+#[test]
+fn test_beacon_chain() {
+    let vdf = TrapdoorVDF::setup("2", RSA_2048);
+    let delay = Delay::new(12).unwrap();
+
+    let mut beacon = Beacon::new(&vdf, delay.clone(), Sha256Digest, 64);
+    for seed in [&b"round 0 reveals"[..], b"round 1 reveals", b"round 2 reveals"] {
+        beacon.next_round(seed);
+    }
+    let mut rounds = beacon.rounds().to_vec();
+    assert_eq!(verify_chain(&vdf, &delay, &Sha256Digest, &rounds), Ok(()));
+
+    // a different seed in round 1 no longer hashes to its input
+    let mut biased = rounds.clone();
+    biased[1].seed = b"biased".to_vec();
+    assert_eq!(verify_chain(&vdf, &delay, &Sha256Digest, &biased), Err(1));
+
+    // round 2 with 'm - y' and a proof made for it: 'pi^l * x^r' is ±y, but the output is not
+    // canonical
+    let r = &mut rounds[2];
+    let m = &vdf.group.m;
+    let negated = Integer::from(m - &r.proof.output);
+    let e = vdf.exponent(&delay).iter().fold(Integer::from(1u32), |acc, f| acc * f);
+    let l = wesolowski::challenge(&vdf.group, &r.input, &negated);
+    r.proof = Proof {
+        pi: r.input.clone().pow_mod(&(e / l), m).unwrap(),
+        output: negated,
+    };
+    r.randomness = Sha256Digest.digest(m, &r.proof.output);
+    assert_eq!(verify_chain(&vdf, &delay, &Sha256Digest, &rounds), Err(2));
+}
+
+#[test]
+fn test_beacon_poseidon_digest() {
+    let vdf = TrapdoorVDF::setup("2", RSA_2048);
+    let delay = Delay::new(11).unwrap();
+    let digest = PoseidonDigest(Poseidon::<Bn256>::default());
+
+    let mut beacon = Beacon::new(&vdf, delay.clone(), PoseidonDigest(Poseidon::<Bn256>::default()), 64);
+    beacon.next_round(b"entropy");
+    beacon.next_round(b"more entropy");
+    assert_eq!(verify_chain(&vdf, &delay, &digest, beacon.rounds()), Ok(()));
+
+    // only the low CAPACITY bits of the digest are set
+    assert!(beacon.rounds().iter().all(|r| r.randomness[0] >> 5 == 0));
+}
@@ -0,0 +1,241 @@
+extern crate sapling_crypto;
+extern crate vdf_snark;
+
+use bellman_bignat::hash::hashes::Poseidon;
+use rug::Integer;
+use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+use sapling_crypto::bellman::pairing::ff::{Field, PrimeField};
+use sapling_crypto::bellman::Circuit;
+use sapling_crypto::circuit::test::TestConstraintSystem;
+use std::time::Instant;
+
+use vdf_snark::circuit::batch::BatchCircuit;
+use vdf_snark::circuit::order::{open_order, open_order_with_output, seal_order, Order, OrderBounds, Side};
+use vdf_snark::circuit::preimage::{commit_order, order_base, PreimageCircuit};
+use vdf_snark::circuit::squaring::{constraint_growth, RepeatedSquaringCircuit};
+use vdf_snark::circuit::wesolowski;
+use vdf_snark::homomorphic::PuzzleParams;
+use vdf_snark::params::{CircuitParams, ParamsError};
+use vdf_snark::profile::{profile_circuits, profile_gadgets};
+use vdf_snark::wesolowski::CHALLENGE_BITS;
+use vdf_snark::{Delay, TrapdoorVDF};
+
+fn params() -> CircuitParams {
+    CircuitParams::new(32, 512, CHALLENGE_BITS as usize).unwrap()
+}
+
+#[test]
+fn test_repeated_squaring_circuit() {
+
+    let vdf = TrapdoorVDF::setup_with_random("2", "512");
+    let x = Integer::from(1337);
+
+    let circuit = RepeatedSquaringCircuit::new(params(), 8, &vdf.group.m, &x).unwrap();
+    let mut cs = TestConstraintSystem::<Bn256>::new();
+    let start = Instant::now();
+    circuit.clone().synthesize(&mut cs).unwrap();
+    println!("synthesis : {:?}, constraints : {}", start.elapsed(), cs.num_constraints());
+    assert!(cs.is_satisfied());
+
+    // wrong output
+    let mut bad = circuit;
+    bad.y = bad.y.map(|y| y + 1);
+    let mut cs = TestConstraintSystem::<Bn256>::new();
+    bad.synthesize(&mut cs).unwrap();
+    assert!(!cs.is_satisfied());
+
+    // the right output plus the modulus, which fits the 512-bit layout of a 256-bit modulus
+    let vdf = TrapdoorVDF::setup_with_random("2", "256");
+    let circuit = RepeatedSquaringCircuit::new(params(), 8, &vdf.group.m, &x).unwrap();
+    let mut cs = TestConstraintSystem::<Bn256>::new();
+    circuit.clone().synthesize(&mut cs).unwrap();
+    assert!(cs.is_satisfied());
+    let mut bad = circuit;
+    bad.y = bad.y.map(|y| y + &vdf.group.m);
+    let mut cs = TestConstraintSystem::<Bn256>::new();
+    bad.synthesize(&mut cs).unwrap();
+    assert!(!cs.is_satisfied());
+}
+
+#[test]
+fn test_repeated_squaring_growth() {
+
+    let growth = constraint_growth::<Bn256>(params(), &[0, 1, 2, 4, 8, 16]).unwrap();
+    for (t, n) in &growth {
+        println!("T = {:>2} : {} constraints", t, n);
+    }
+    // every squaring costs the same
+    let per_square = growth[1].1 - growth[0].1;
+    for (t, n) in &growth {
+        assert_eq!(*n, growth[0].1 + t * per_square);
+    }
+}
+
+#[test]
+fn test_order_circuit() {
+
+    // params set up by a party the traders and the operator both trust
+    let vdf = TrapdoorVDF::setup_with_random("2", "512");
+    let puzzles = PuzzleParams::setup(&vdf, &Delay::new(10).unwrap()).unwrap();
+
+    //== Trader side ==//
+    let hasher = Poseidon::<Bn256>::default();
+    let bounds = OrderBounds { price_bits: 32, size_bits: 16 };
+    let order = Order { price: 1_050, size: 25, side: Side::Sell };
+    let (puzzle, circuit) = seal_order(&puzzles, &hasher, params(), bounds, &order).unwrap();
+
+    let mut cs = TestConstraintSystem::<Bn256>::new();
+    let start = Instant::now();
+    circuit.clone().synthesize(&mut cs).unwrap();
+    println!("synthesis : {:?}, constraints : {}", start.elapsed(), cs.num_constraints());
+    assert!(cs.is_satisfied());
+
+    //== Operator side ==//
+    // after the delay the order matches what was proven
+    assert_eq!(open_order(&puzzle, &hasher, &params()), Some(order));
+
+    // a key other than the committed one
+    let mut bad = circuit.clone();
+    bad.key_commitment = bad.key_commitment.map(|mut c| { c.double(); c });
+    let mut cs = TestConstraintSystem::<Bn256>::new();
+    bad.synthesize(&mut cs).unwrap();
+    assert!(!cs.is_satisfied());
+
+    // randomness other than the one behind the base, e.g. to derive the key from another output
+    let mut bad = circuit.clone();
+    bad.randomness = bad.randomness.map(|r| r + 1);
+    let mut cs = TestConstraintSystem::<Bn256>::new();
+    bad.synthesize(&mut cs).unwrap();
+    assert!(!cs.is_satisfied());
+
+    // the trapdoor of the modulus gives the same output, so the same order
+    assert_eq!(open_order_with_output(&puzzle, &hasher, &params(), &vdf.eval_delay_with_trapdoor(&puzzle.base, &puzzle.delay)), Some(order));
+
+    // a range wider than the field is rejected rather than checked
+    let mut wide = circuit.clone();
+    wide.bounds = OrderBounds { price_bits: 300, size_bits: 16 };
+    let mut cs = TestConstraintSystem::<Bn256>::new();
+    assert!(wide.synthesize(&mut cs).is_err());
+
+    // an order out of range does not prove
+    let big = Order { price: 1 << 32, size: 25, side: Side::Buy };
+    assert!(!bounds.contains(&big));
+    let (_, circuit) = seal_order(&puzzles, &hasher, params(), bounds, &big).unwrap();
+    let mut cs = TestConstraintSystem::<Bn256>::new();
+    circuit.synthesize(&mut cs).unwrap();
+    assert!(!cs.is_satisfied());
+}
+
+#[test]
+fn test_profile() {
+
+    let hasher = Poseidon::<Bn256>::default();
+    let gadgets = profile_gadgets::<Bn256, _>(&hasher, &params()).unwrap();
+    for profile in &gadgets {
+        println!("{}", profile);
+    }
+    let mult_mod = gadgets[0].scope("bignat mult_mod").unwrap();
+    assert!(mult_mod.constraints > 0 && mult_mod.density() > 1.0);
+
+    // the squaring circuit is t mult_mods plus its inputs
+    let circuits = profile_circuits::<Bn256, _>(&hasher, &params(), 4).unwrap();
+    for profile in &circuits {
+        println!("{}", profile);
+    }
+    assert_eq!(circuits[0].scope("square").unwrap().constraints, 4 * mult_mod.constraints);
+}
+
+#[test]
+fn test_circuit_params() {
+
+    let vdf = TrapdoorVDF::setup_with_random("2", "512");
+    let m = &vdf.group.m;
+    assert_eq!(params().n_limbs, 16);
+    assert_eq!(params().check::<Fr>(), Ok(()));
+
+    // witnesses outside Z_m or a modulus wider than the layout are rejected up front
+    assert_eq!(RepeatedSquaringCircuit::new(params(), 2, m, m).err(), Some(ParamsError::ValueOutOfRange));
+    let small = CircuitParams::new(32, 256, CHALLENGE_BITS as usize).unwrap();
+    assert_eq!(small.check_modulus(m), Err(ParamsError::ModulusTooLarge));
+
+    // columns of 128-bit limb products overflow the field
+    let wide = CircuitParams::new(128, 512, CHALLENGE_BITS as usize).unwrap();
+    assert_eq!(wide.check::<Fr>(), Err(ParamsError::LimbTooWide));
+
+    // a zero limb width is rejected before sizing the limbs
+    assert_eq!(CircuitParams::new(0, 512, CHALLENGE_BITS as usize), Err(ParamsError::EmptyLimbs));
+}
+
+#[test]
+fn test_batch_circuit() {
+
+    let vdf = TrapdoorVDF::setup_with_random("2", "512");
+    let delay = Delay::new(6).unwrap();
+    let hasher = Poseidon::<Bn256>::default();
+    let bases = [Integer::from(1337), Integer::from(7331), Integer::from(42)];
+
+    let circuit = BatchCircuit::prove(&vdf, &delay, hasher.clone(), params(), &bases).unwrap();
+    for (x, proof) in circuit.instances.as_ref().unwrap() {
+        assert_eq!(proof.output, vdf.eval_delay(x, &delay));
+        assert!(wesolowski::verify(&vdf.group.m, &vdf.exponent(&delay), &hasher, &params(), x, proof));
+    }
+
+    let mut cs = TestConstraintSystem::<Bn256>::new();
+    let start = Instant::now();
+    circuit.clone().synthesize(&mut cs).unwrap();
+    println!("synthesis : {:?}, constraints : {}", start.elapsed(), cs.num_constraints());
+    assert!(cs.is_satisfied());
+    assert!(cs.verify(&circuit.public_inputs()));
+
+    // one wrong output fails the batch
+    let mut bad = circuit;
+    bad.instances.as_mut().unwrap()[1].1.output += 1;
+    let mut cs = TestConstraintSystem::<Bn256>::new();
+    bad.synthesize(&mut cs).unwrap();
+    assert!(!cs.is_satisfied());
+}
+
+#[test]
+fn test_preimage_circuit() {
+
+    let vdf = TrapdoorVDF::setup_with_random("2", "512");
+    let m = &vdf.group.m;
+    let hasher = Poseidon::<Bn256>::default();
+    let order = Order { price: 1_050, size: 25, side: Side::Buy };
+    let salt = Fr::from_str("982451653").unwrap();
+
+    let circuit = PreimageCircuit::new(hasher.clone(), params(), m, &order, salt).unwrap();
+    let c = commit_order(&hasher, &order, salt);
+    assert_eq!(circuit.base, Some(order_base(&hasher, &params(), m, c).unwrap()));
+
+    // a layout that does not fit the field is rejected before deriving the base
+    let wide = CircuitParams::new(256, 512, CHALLENGE_BITS as usize).unwrap();
+    assert_eq!(PreimageCircuit::new(hasher.clone(), wide, m, &order, salt).err(), Some(ParamsError::LimbTooWide));
+    assert_eq!(order_base(&hasher, &wide, m, c), Err(ParamsError::LimbTooWide));
+
+    let mut cs = TestConstraintSystem::<Bn256>::new();
+    let start = Instant::now();
+    circuit.clone().synthesize(&mut cs).unwrap();
+    println!("synthesis : {:?}, constraints : {}", start.elapsed(), cs.num_constraints());
+    assert!(cs.is_satisfied());
+    assert!(cs.verify(&circuit.public_inputs()));
+
+    // another order under the same commitment and base
+    let mut bad = circuit;
+    bad.order = Some(Order { price: 1_051, ..order });
+    let mut cs = TestConstraintSystem::<Bn256>::new();
+    bad.synthesize(&mut cs).unwrap();
+    assert!(!cs.is_satisfied());
+
+    // the base plus the modulus, which fits the 512-bit layout of a 256-bit modulus
+    let small = TrapdoorVDF::setup_with_random("2", "256");
+    let circuit = PreimageCircuit::new(hasher.clone(), params(), &small.group.m, &order, salt).unwrap();
+    let mut cs = TestConstraintSystem::<Bn256>::new();
+    circuit.clone().synthesize(&mut cs).unwrap();
+    assert!(cs.is_satisfied());
+    let mut bad = circuit;
+    bad.base = bad.base.map(|b| b + &small.group.m);
+    let mut cs = TestConstraintSystem::<Bn256>::new();
+    bad.synthesize(&mut cs).unwrap();
+    assert!(!cs.is_satisfied());
+}
@@ -0,0 +1,32 @@
+extern crate vdf_snark;
+
+use crate::vdf_snark::continuous::{ChainState, ContinuousVDF};
+use crate::vdf_snark::RSA_2048;
+use rug::Integer;
+
+#[test]
+fn test_continuous_vdf() {
+    let vdf = vdf_snark::TrapdoorVDF::setup("2", RSA_2048);
+    let delta = 2000;
+
+    let segments: Vec<_> = ContinuousVDF::new(&vdf, &Integer::from(3u32), delta, 16).take(4).collect();
+
+    // full chain from the seed
+    let mut state = ChainState {
+        index: 0,
+        output: Integer::from(3u32),
+    };
+    for segment in &segments {
+        assert!(state.advance(&vdf, delta, segment));
+    }
+    assert_eq!(state.output, Integer::from(3u32).pow_mod(&(Integer::from(1u32) << (4 * delta)), &vdf.group.m).unwrap());
+
+    // join mid-stream from the second output
+    let mut joined = ChainState {
+        index: segments[1].index,
+        output: segments[1].output().clone(),
+    };
+    assert!(!joined.advance(&vdf, delta, &segments[3]));
+    assert!(joined.advance(&vdf, delta, &segments[2]));
+    assert!(!joined.advance(&vdf, delta, &segments[2]));
+}
@@ -0,0 +1,55 @@
+extern crate sapling_crypto;
+extern crate vdf_snark;
+
+use rug::Integer;
+use sapling_crypto::bellman::pairing::bn256::Bn256;
+use std::fs;
+use std::time::Instant;
+
+use vdf_snark::circuit::squaring::RepeatedSquaringCircuit;
+use vdf_snark::keys::{verify, KeyError, KeyStore};
+use vdf_snark::params::CircuitParams;
+use vdf_snark::TrapdoorVDF;
+
+#[test]
+fn test_key_store() {
+
+    let dir = std::env::temp_dir().join(format!("vdf_snark_keys_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    let vdf = TrapdoorVDF::setup_with_random("2", "256");
+    let params = CircuitParams::new(32, 256, 128).unwrap();
+    let circuit = RepeatedSquaringCircuit::new(params, 2, &vdf.group.m, &Integer::from(1337)).unwrap();
+
+    let store = KeyStore::<Bn256>::new(&dir);
+    let gen_time = Instant::now();
+    store.params(&circuit).unwrap();
+    println!("generate : {:?}", gen_time.elapsed());
+    assert!(store.path(&circuit).exists());
+
+    // a fresh store loads the cached file instead of regenerating
+    let store = KeyStore::<Bn256>::new(&dir);
+    let load_time = Instant::now();
+    let proof = store.prove(&circuit, circuit.clone()).unwrap();
+    println!("load and prove : {:?}", load_time.elapsed());
+    let vk = store.verifying_key(&circuit).unwrap();
+    assert!(verify(&vk, &proof, &circuit.public_inputs::<Bn256>()));
+
+    let mut wrong = circuit.public_inputs::<Bn256>();
+    wrong.swap(8, 9);
+    assert!(!verify(&vk, &proof, &wrong));
+
+    // keys of another shape under this circuit's name are rejected
+    let other = RepeatedSquaringCircuit::blank(params, 3);
+    fs::copy(store.path(&circuit), store.path(&other)).unwrap();
+    assert!(matches!(KeyStore::<Bn256>::new(&dir).params(&other), Err(KeyError::ShapeMismatch)));
+
+    // so is a file that does not match its content hash
+    let mut bytes = fs::read(store.path(&circuit)).unwrap();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+    fs::write(store.path(&circuit), bytes).unwrap();
+    assert!(matches!(KeyStore::<Bn256>::new(&dir).params(&circuit), Err(KeyError::Corrupt)));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
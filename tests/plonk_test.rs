@@ -0,0 +1,64 @@
+extern crate vdf_snark;
+
+use dusk_plonk::prelude::*;
+use rug::Integer;
+use std::time::Instant;
+
+use vdf_snark::params::{CircuitParams, ParamsError};
+use vdf_snark::plonk::{self, CommitmentCircuit, WesolowskiCircuit};
+use vdf_snark::{Delay, TrapdoorVDF};
+
+
+#[test]
+fn test_plonk_universal_setup() {
+
+    let vdf = TrapdoorVDF::setup_with_random("2", "256");
+    let delay = Delay::new(10).unwrap();
+    let params = CircuitParams::new(32, 256, 128).unwrap();
+    let x = Integer::from(1337);
+    let vdf_proof = vdf.prove_batch_quotient(&[x.clone()], &delay).remove(0);
+    let mut wesolowski = WesolowskiCircuit::new(params, &vdf, &delay, &x, &vdf_proof).unwrap();
+    let mut commitment = CommitmentCircuit::new(params, &wesolowski.y).unwrap();
+    println!("gates : wesolowski {}, commitment {}", wesolowski.padded_gates(), commitment.padded_gates());
+
+    // one setup for both circuits
+    let setup_time = Instant::now();
+    let pp = plonk::universal_setup(wesolowski.padded_gates().max(commitment.padded_gates())).unwrap();
+    println!("setup : {:?}", setup_time.elapsed());
+
+    let (pk, vd) = plonk::compile(&pp, &mut WesolowskiCircuit::blank(params).unwrap()).unwrap();
+    let prove_time = Instant::now();
+    let proof = plonk::prove(&pp, &pk, &mut wesolowski).unwrap();
+    println!("prove wesolowski : {:?}", prove_time.elapsed());
+    let inputs = wesolowski.public_inputs();
+    assert!(plonk::verify::<WesolowskiCircuit>(&pp, &vd, &proof, &inputs));
+
+    let other = vdf.prove_batch_quotient(&[Integer::from(1338)], &delay).remove(0);
+    let wrong = WesolowskiCircuit::new(params, &vdf, &delay, &Integer::from(1338), &other).unwrap().public_inputs();
+    assert!(!plonk::verify::<WesolowskiCircuit>(&pp, &vd, &proof, &wrong));
+
+    // an output off by the modulus is not reduced and does not prove
+    let mut unreduced = WesolowskiCircuit::new(params, &vdf, &delay, &x, &vdf_proof).unwrap();
+    unreduced.y += &vdf.group.m;
+    let inputs = unreduced.public_inputs();
+    let proved = plonk::prove(&pp, &pk, &mut unreduced);
+    assert!(proved.map_or(true, |p| !plonk::verify::<WesolowskiCircuit>(&pp, &vd, &p, &inputs)));
+
+    // the other representative of the output, m - y, is rejected up front and does not prove
+    let negated = vdf_snark::wesolowski::Proof {
+        output: Integer::from(&vdf.group.m - &vdf_proof.output),
+        pi: vdf_proof.pi.clone(),
+    };
+    assert_eq!(WesolowskiCircuit::new(params, &vdf, &delay, &x, &negated).err(), Some(ParamsError::ValueOutOfRange));
+    let mut flipped = WesolowskiCircuit::new(params, &vdf, &delay, &x, &vdf_proof).unwrap();
+    flipped.y = negated.output;
+    let inputs = flipped.public_inputs();
+    let proved = plonk::prove(&pp, &pk, &mut flipped);
+    assert!(proved.map_or(true, |p| !plonk::verify::<WesolowskiCircuit>(&pp, &vd, &p, &inputs)));
+
+    let (pk, vd) = plonk::compile(&pp, &mut CommitmentCircuit::blank(params).unwrap()).unwrap();
+    let proof = plonk::prove(&pp, &pk, &mut commitment).unwrap();
+    assert!(plonk::verify::<CommitmentCircuit>(&pp, &vd, &proof, &commitment.public_inputs()));
+    let other = CommitmentCircuit::new(params, &Integer::from(&wesolowski.y + 1)).unwrap();
+    assert!(!plonk::verify::<CommitmentCircuit>(&pp, &vd, &proof, &other.public_inputs()));
+}
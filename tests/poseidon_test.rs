@@ -40,6 +40,48 @@ fn test_ark_sponge_poseidon_hash() {
     println!("{:?}", squezzed1);
 }
 
+#[test]
+fn test_generated_poseidon_parameters() {
+    use ark_sponge::poseidon::PoseidonSponge;
+    use ark_test_curves::bls12_381::Fr;
+    use vdf_snark::poseidon::{mds_is_secure, round_numbers, PoseidonError, PoseidonParams};
+
+    // the published round numbers of 'poseidonperm_x5_255_3' and 'poseidonperm_x5_255_5'
+    assert_eq!(round_numbers::<Fr>(3, 5, 128), Ok((8, 57)));
+    assert_eq!(round_numbers::<Fr>(5, 5, 128), Ok((8, 60)));
+    // x^3 is not a permutation of BLS12-381 Fr
+    assert_eq!(round_numbers::<Fr>(3, 3, 128), Err(PoseidonError::AlphaNotPermutation));
+
+    // first round constant of the reference 'poseidonperm_x5_255_3' tables
+    let params = PoseidonParams::<Fr>::with_rounds(3, 5, 8, 57).unwrap();
+    assert_eq!(
+        params.ark[0][0],
+        Fr::from_str("48991097081732275468845314168021420565497297775988823234113406403095118809216").map_err(|_| ()).unwrap()
+    );
+    assert_eq!(params.ark.len(), 65);
+    assert!(mds_is_secure(&params.mds));
+
+    // a matrix whose square is the identity keeps the states with 'x_0 = 0' every two rounds
+    let swap = vec![vec![Fr::from(0u64), Fr::from(1u64)], vec![Fr::from(1u64), Fr::from(0u64)]];
+    assert!(!mds_is_secure(&swap));
+
+    // generation is deterministic, and the tables plug into ark-sponge
+    let params = PoseidonParams::<Fr>::generate(3, 17, 128).unwrap();
+    assert_eq!(params, PoseidonParams::<Fr>::generate(3, 17, 128).unwrap());
+    let sponge_param = PoseidonParameters {
+        full_rounds: params.full_rounds,
+        partial_rounds: params.partial_rounds,
+        alpha: params.alpha,
+        ark: params.ark,
+        mds: params.mds,
+        rate: 2,
+        capacity: 1,
+    };
+    let mut sponge = PoseidonSponge::<Fr>::new(&sponge_param);
+    sponge.absorb(&Fr::from(114514u128));
+    println!("{:?}", sponge.squeeze_native_field_elements(2));
+}
+
 /// Generate default parameters (bls381-fr-only) for alpha = 17, state-size = 8
 pub(crate) fn poseidon_parameters_for_test<F: PrimeField>() -> PoseidonParameters<F> {
     let alpha = 17;
@@ -0,0 +1,217 @@
+extern crate vdf_snark;
+
+use rug::Integer;
+use vdf_snark::protocol::{Dispute, Event, Message, Operator, OrderState, ProtocolError, Trader, TraderOrderState};
+use vdf_snark::timed_signature::SigningKey;
+use vdf_snark::timelock::TimelockError;
+use vdf_snark::wesolowski::{self, Proof};
+use vdf_snark::{Delay, TrapdoorVDF};
+
+// a proof that 'x^e % N' is 'z' for any z, made with φ(N): 'pi = (z * x^-r)^(l^-1 mod φ(N))'
+fn forge(vdf: &TrapdoorVDF, x: &Integer, delay: &Delay, z: &Integer) -> Proof {
+    let m = &vdf.group.m;
+    let e = vdf.exponent(delay).iter().fold(Integer::from(1u32), |acc, f| acc * f);
+    let l = wesolowski::challenge(&vdf.group, x, z);
+    let r = Integer::from(&e % &l);
+    let base = Integer::from(z * x.clone().pow_mod(&-r, m).unwrap()) % m;
+    let pi = base.pow_mod(&l.invert(&vdf.trapdoor).unwrap(), m).unwrap();
+    Proof {
+        output: z.clone(),
+        pi: pi,
+    }
+}
+
+#[test]
+fn test_commit_reveal_flow() {
+    let delay = Delay::new(13).unwrap();
+
+    let mut operator = Operator::new();
+
+    //== Trader side ==//
+    let mut trader = Trader::new(7, TrapdoorVDF::setup_with_random("2", "512"), delay.clone(), *operator.public_key());
+    let announce = trader.announce().unwrap();
+    let seal_a = trader.seal(1, b"BUY 10 @ 99").unwrap();
+    let seal_b = trader.seal(2, b"SELL 4 @ 101").unwrap();
+    let reveal_a = trader.reveal(1).unwrap();
+
+    //== Operator side ==//
+    assert_eq!(operator.handle(&seal_a), Err(ProtocolError::UnknownTrader));
+    assert_eq!(operator.handle(&announce), Ok(Event::Announced(7)));
+    assert_eq!(operator.handle(&seal_b), Err(ProtocolError::OutOfOrder));
+    assert_eq!(operator.handle(&seal_a), Ok(Event::Sealed(7, 1)));
+    assert_eq!(operator.handle(&seal_a), Err(ProtocolError::Replay));
+    assert_eq!(operator.handle(&seal_b), Ok(Event::Sealed(7, 2)));
+    assert_eq!(operator.handle(&reveal_a), Ok(Event::Revealed(7, 1, b"BUY 10 @ 99".to_vec())));
+
+    // order 2 is never revealed: the operator forces it open
+    assert_eq!(operator.force_open(7, 1), Err(ProtocolError::InvalidTransition));
+    let forced = operator.force_open(7, 2).unwrap();
+    assert_eq!(operator.order_state(7, 2), Some(&OrderState::ForceOpened(b"SELL 4 @ 101".to_vec())));
+
+    //== Trader side ==//
+    assert_eq!(trader.handle(&forced), Ok(None));
+    assert_eq!(trader.order_state(2), Some(&TraderOrderState::ForceOpened));
+    assert_eq!(trader.handle(&forced), Err(ProtocolError::Replay));
+
+    //== Audit ==//
+    let audited = Operator::audit(operator.public_key(), operator.transcript()).unwrap();
+    assert_eq!(audited.order_state(7, 1), operator.order_state(7, 1));
+    assert_eq!(audited.order_state(7, 2), operator.order_state(7, 2));
+}
+
+#[test]
+fn test_rejected_message_uses_seq() {
+    let delay = Delay::new(12).unwrap();
+    let mut operator = Operator::new();
+    let mut trader = Trader::new(5, TrapdoorVDF::setup_with_random("2", "512"), delay, *operator.public_key());
+    operator.handle(&trader.announce().unwrap()).unwrap();
+    let seal_a = trader.seal(1, b"BUY 2 @ 70").unwrap();
+    let seal_b = trader.seal(2, b"SELL 2 @ 72").unwrap();
+
+    // the first seal arrives with the wrong delay and is rejected, but uses up its seq
+    let mut bad = seal_a.clone();
+    if let Message::Seal(s) = &mut bad {
+        s.commitment.delay = Delay::new(13).unwrap();
+    }
+    assert_eq!(operator.handle(&bad), Err(ProtocolError::ParameterMismatch));
+    assert_eq!(operator.handle(&seal_a), Err(ProtocolError::Replay));
+    assert_eq!(operator.handle(&seal_b), Ok(Event::Sealed(5, 2)));
+
+    let audited = Operator::audit(operator.public_key(), operator.transcript()).unwrap();
+    assert_eq!(audited.order_state(5, 1), None);
+    assert_eq!(audited.order_state(5, 2), Some(&OrderState::Sealed));
+}
+
+#[test]
+fn test_dispute_forced_open() {
+    let delay = Delay::new(12).unwrap();
+    let key = SigningKey::generate();
+    let mut operator = Operator::with_key(key.clone());
+    let mut trader = Trader::new(3, TrapdoorVDF::setup_with_random("2", "512"), delay, key.pk);
+    operator.handle(&trader.announce().unwrap()).unwrap();
+    operator.handle(&trader.seal(9, b"BUY 1 @ 50").unwrap()).unwrap();
+    operator.handle(&trader.seal(10, b"SELL 1 @ 60").unwrap()).unwrap();
+
+    // the operator reports and signs a different order than the one sealed
+    let mut forced = operator.force_open(3, 9).unwrap();
+    if let Message::ForcedOpen(f) = &mut forced {
+        f.message = b"BUY 1 @ 5".to_vec();
+        f.signature = key.sign(&f.signed_bytes());
+    }
+    let dispute = trader.handle(&forced).unwrap().unwrap();
+    assert_eq!(trader.order_state(9), Some(&TraderOrderState::Disputed));
+
+    // the signed receipt convicts the operator
+    assert_eq!(operator.handle(&dispute), Ok(Event::Disputed(3, 9, b"BUY 1 @ 50".to_vec())));
+    assert_eq!(operator.order_state(3, 9), Some(&OrderState::Disputed(b"BUY 1 @ 50".to_vec())));
+
+    // a tampered receipt is not signed by the operator
+    let forced = operator.force_open(3, 10).unwrap();
+    let mut tampered = forced.clone();
+    if let Message::ForcedOpen(f) = &mut tampered {
+        f.message = b"SELL 1 @ 6".to_vec();
+    }
+    assert_eq!(trader.handle(&tampered), Err(ProtocolError::InvalidSignature));
+    assert_eq!(trader.handle(&forced), Ok(None));
+    let mut dispute = dispute;
+    if let (Message::Dispute(d), Message::ForcedOpen(f)) = (&mut dispute, &tampered) {
+        d.seq += 1;
+        d.order = 10;
+        d.receipt = f.clone();
+    }
+    assert_eq!(operator.handle(&dispute), Err(ProtocolError::DisputeRejected));
+
+    assert!(Operator::audit(operator.public_key(), operator.transcript()).is_ok());
+}
+
+#[test]
+fn test_forged_opening_rejected() {
+    let delay = Delay::new(12).unwrap();
+    let vdf = TrapdoorVDF::setup_with_random("2", "512");
+    let mut forger = TrapdoorVDF::setup("2", vdf.group.m.to_string_radix(10).as_str());
+    forger.trapdoor = vdf.trapdoor.clone();
+
+    let mut operator = Operator::new();
+    let mut trader = Trader::new(4, vdf, delay.clone(), *operator.public_key());
+    operator.handle(&trader.announce().unwrap()).unwrap();
+    let seal = trader.seal(1, b"BUY 3 @ 40").unwrap();
+    operator.handle(&seal).unwrap();
+    let base = match &seal {
+        Message::Seal(s) => s.commitment.base.clone(),
+        _ => unreachable!(),
+    };
+
+    // the trader reveals another output, with a proof made from its trapdoor
+    let mut reveal = trader.reveal(1).unwrap();
+    if let Message::Reveal(r) = &mut reveal {
+        r.opening = forge(&forger, &base, &delay, &Integer::from(&r.opening.output + 1u32));
+        assert!(forger.verify_delay(&base, &delay, &r.opening));
+    }
+    assert_eq!(operator.handle(&reveal), Err(ProtocolError::Timelock(TimelockError::Decrypt)));
+    assert_eq!(operator.order_state(4, 1), Some(&OrderState::Sealed));
+
+    // and disputes the honest forced opening with a forged one
+    let receipt = match operator.force_open(4, 1).unwrap() {
+        Message::ForcedOpen(f) => f,
+        _ => unreachable!(),
+    };
+    let opening = forge(&forger, &base, &delay, &Integer::from(&receipt.opening.output + 1u32));
+    let dispute = Message::Dispute(Dispute {
+        trader: 4,
+        seq: 4,
+        order: 1,
+        message: b"BUY 3 @ 4".to_vec(),
+        opening: opening,
+        receipt: receipt,
+    });
+    assert_eq!(operator.handle(&dispute), Err(ProtocolError::Timelock(TimelockError::Decrypt)));
+    assert_eq!(operator.order_state(4, 1), Some(&OrderState::ForceOpened(b"BUY 3 @ 40".to_vec())));
+}
+
+#[test]
+fn test_fair_ordering_simulation() {
+    use vdf_snark::simulator::{simulate, OperatorKind, SimConfig};
+
+    let config = SimConfig {
+        traders: 3,
+        orders_per_trader: 3,
+        delay: Delay::new(12).unwrap(),
+        seed: 42,
+        ..SimConfig::default()
+    };
+
+    let honest = simulate(config.clone()).unwrap();
+    assert_eq!(honest.orders, 9);
+    assert_eq!(honest.early_reads, 0);
+    assert_eq!(honest.violations, 0);
+    assert_eq!(honest.rejected, 0);
+    assert_eq!(honest.reveal_latency.count + honest.forced_latency.count, 9);
+
+    // the same seed replays the same run
+    let again = simulate(config.clone()).unwrap();
+    assert_eq!(again.reveal_latency, honest.reveal_latency);
+    assert_eq!(again.forced_latency, honest.forced_latency);
+
+    // the delay outlasts the batch window: the adversary reads nothing in time
+    let slow = simulate(SimConfig {
+        operator: OperatorKind::Adversarial,
+        delay_ticks: 2 * config.batch_window,
+        ..config.clone()
+    })
+    .unwrap();
+    assert_eq!(slow.early_reads, 0);
+    assert_eq!(slow.violations, 0);
+
+    // a delay much shorter than the window lets the adversary front-run
+    let fast = simulate(SimConfig {
+        operator: OperatorKind::Adversarial,
+        delay_ticks: 10,
+        adversary_speed: 2.0,
+        ..config
+    })
+    .unwrap();
+    assert!(fast.early_reads > 0);
+    assert!(fast.violations > 0 && fast.violations <= fast.early_reads);
+    assert_eq!(fast.reveal_latency.count + fast.forced_latency.count, 9);
+    println!("{:?}", fast);
+}
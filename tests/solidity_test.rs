@@ -0,0 +1,91 @@
+extern crate sapling_crypto;
+extern crate vdf_snark;
+
+use revm::primitives::{Address, ExecutionResult, Output, TransactTo};
+use revm::{Evm, InMemoryDB};
+use rug::Integer;
+use sapling_crypto::bellman::pairing::bn256::Bn256;
+use semver::Version;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use vdf_snark::circuit::squaring::RepeatedSquaringCircuit;
+use vdf_snark::keys::KeyStore;
+use vdf_snark::params::CircuitParams;
+use vdf_snark::solidity::{encode_calldata, export_verifier, pairing_input};
+use vdf_snark::TrapdoorVDF;
+
+fn transact(db: &mut InMemoryDB, to: TransactTo, data: Vec<u8>) -> ExecutionResult {
+    let mut evm = Evm::builder()
+        .with_db(db)
+        .modify_tx_env(|tx| {
+            tx.caller = Address::with_last_byte(0xaa);
+            tx.transact_to = to;
+            tx.data = data.into();
+            tx.gas_limit = 30_000_000;
+        })
+        .build();
+    evm.transact_commit().unwrap()
+}
+
+fn output(result: ExecutionResult) -> Vec<u8> {
+    match result {
+        ExecutionResult::Success { output: Output::Call(bytes), gas_used, .. } => {
+            println!("gas used : {}", gas_used);
+            bytes.to_vec()
+        }
+        r => panic!("call failed : {:?}", r),
+    }
+}
+
+// the compiler the contract is tested with, fetched by 'svm' rather than taken from PATH
+const SOLC_VERSION: &str = "0.8.19";
+
+// creation code of the contract in 'path'
+fn compile(path: &Path) -> Vec<u8> {
+    let solc = svm::blocking_install(&Version::parse(SOLC_VERSION).unwrap()).expect("cannot install solc");
+    let out = Command::new(solc).args(["--bin", "--optimize"]).arg(path).output().unwrap();
+    assert!(out.status.success(), "compilation failed : {}", String::from_utf8_lossy(&out.stderr));
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    let bin = stdout.lines().skip_while(|l| !l.starts_with("Binary")).nth(1).unwrap();
+    (0..bin.len()).step_by(2).map(|i| u8::from_str_radix(&bin[i..i + 2], 16).unwrap()).collect()
+}
+
+fn is_true(word: &[u8]) -> bool {
+    word.len() == 32 && word[..31].iter().all(|b| *b == 0) && word[31] == 1
+}
+
+#[test]
+fn test_solidity_verifier() {
+
+    let dir = std::env::temp_dir().join(format!("vdf_snark_solidity_{}", std::process::id()));
+    let vdf = TrapdoorVDF::setup_with_random("2", "256");
+    let circuit = RepeatedSquaringCircuit::new(CircuitParams::new(32, 256, 128).unwrap(), 2, &vdf.group.m, &Integer::from(1337)).unwrap();
+
+    let store = KeyStore::<Bn256>::new(&dir);
+    let params = store.params(&circuit).unwrap();
+    let proof = store.prove(&circuit, circuit.clone()).unwrap();
+    let inputs = circuit.public_inputs::<Bn256>();
+    let mut wrong = inputs.clone();
+    wrong.swap(8, 9);
+
+    // the pairing check the verifier performs, run on the EVM's precompile
+    let mut db = InMemoryDB::default();
+    let pairing = TransactTo::Call(Address::with_last_byte(0x08));
+    assert!(is_true(&output(transact(&mut db, pairing.clone(), pairing_input(&params.vk, &proof, &inputs)))));
+    assert!(!is_true(&output(transact(&mut db, pairing, pairing_input(&params.vk, &proof, &wrong)))));
+
+    // the full contract
+    let source = export_verifier(&params.vk, "VdfVerifier");
+    let path = dir.join("VdfVerifier.sol");
+    fs::write(&path, &source).unwrap();
+    let contract = match transact(&mut db, TransactTo::create(), compile(&path)) {
+        ExecutionResult::Success { output: Output::Create(_, Some(address)), .. } => address,
+        r => panic!("deployment failed : {:?}", r),
+    };
+    assert!(is_true(&output(transact(&mut db, TransactTo::Call(contract), encode_calldata(&proof, &inputs)))));
+    assert!(!is_true(&output(transact(&mut db, TransactTo::Call(contract), encode_calldata(&proof, &wrong)))));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,184 @@
+extern crate vdf_snark;
+
+use rug::Integer;
+use std::time::Instant;
+use vdf_snark::timelock::{self, TimelockError};
+use vdf_snark::wesolowski::{self, Proof};
+use vdf_snark::{Delay, TrapdoorVDF};
+
+// a proof that 'x^e % N' is 'z' for any z, made with φ(N): 'pi = (z * x^-r)^(l^-1 mod φ(N))'
+fn forge(vdf: &TrapdoorVDF, x: &Integer, delay: &Delay, z: &Integer) -> Proof {
+    let m = &vdf.group.m;
+    let e = vdf.exponent(delay).iter().fold(Integer::from(1u32), |acc, f| acc * f);
+    let l = wesolowski::challenge(&vdf.group, x, z);
+    let r = Integer::from(&e % &l);
+    let base = Integer::from(z * x.clone().pow_mod(&-r, m).unwrap()) % m;
+    let pi = base.pow_mod(&l.invert(&vdf.trapdoor).unwrap(), m).unwrap();
+    Proof {
+        output: z.clone(),
+        pi: pi,
+    }
+}
+
+#[test]
+fn test_seal_open() {
+    let order = b"BUY 100 ETH @ 1850.25";
+
+    //== Trader side ==//
+    let vdf = vdf_snark::TrapdoorVDF::setup_with_random("2", "512");
+    let setup_seal = Instant::now();
+    let puzzle = timelock::seal(&vdf, &Delay::new(14).unwrap(), order).unwrap();
+    let seal_duration = setup_seal.elapsed();
+
+    //== Operator side ==//
+    let setup_open = Instant::now();
+    assert_eq!(timelock::open(&puzzle).unwrap(), order.to_vec());
+    let open_duration = setup_open.elapsed();
+
+    let mut tampered = puzzle.clone();
+    tampered.delay = Delay::new(13).unwrap();
+    assert_eq!(timelock::open(&tampered), Err(TimelockError::Decrypt));
+
+    let vdf_op = vdf_snark::TrapdoorVDF::setup("2", vdf.group.m.to_string_radix(10).as_str());
+    assert_eq!(timelock::seal(&vdf_op, &Delay::new(14).unwrap(), order), Err(TimelockError::MissingTrapdoor));
+
+    println!("[Duration] seal:[{:?}], open:[{:?}]", seal_duration, open_duration);
+}
+
+#[test]
+fn test_homomorphic_sum() {
+    use rug::Integer;
+    use vdf_snark::homomorphic::PuzzleParams;
+
+    //== Operator side: one-time setup with the trapdoor ==//
+    let vdf = vdf_snark::TrapdoorVDF::setup_with_random("2", "512");
+    let params = PuzzleParams::setup(&vdf, &Delay::new(13).unwrap()).unwrap();
+
+    //== Trader side: sealed bids ==//
+    let bids = [120u32, 75, 310, 5, 990];
+    let puzzles: Vec<_> = bids.iter().map(|b| params.generate(&Integer::from(*b))).collect();
+
+    //== Tally: one sequential solve for the sum ==//
+    let total = params.sum(&puzzles);
+    let setup_solve = Instant::now();
+    let (s, proof) = params.solve_with_proof(&total).unwrap();
+    let solve_duration = setup_solve.elapsed();
+
+    assert_eq!(s, bids.iter().sum::<u32>());
+    assert!(params.verify_solution(&total, &s, &proof));
+    assert!(!params.verify_solution(&total, &Integer::from(1u32), &proof));
+    assert_eq!(params.solve(&puzzles[2]), Some(Integer::from(310u32)));
+
+    println!("[Duration] solve sum of {}:[{:?}]", bids.len(), solve_duration);
+}
+
+#[test]
+fn test_timed_commitment() {
+    use vdf_snark::commitment;
+
+    let order = b"SELL 3 BTC @ 27100";
+
+    //== Trader side ==//
+    let vdf = vdf_snark::TrapdoorVDF::setup_with_random("2", "512");
+    let (c, opening) = commitment::commit(&vdf, &Delay::new(14).unwrap(), order).unwrap();
+
+    //== Operator side ==//
+    assert!(c.verify());
+    assert_eq!(c.open(&opening).unwrap(), order.to_vec());
+
+    // the trader refuses to reveal
+    let setup_force = Instant::now();
+    let (message, forced) = c.force_open().unwrap();
+    let force_duration = setup_force.elapsed();
+    assert_eq!(message, order.to_vec());
+    assert_eq!(forced, opening);
+
+    // the committer proves another output with φ(N); it decodes to a message that does not
+    // match the binding hash
+    let delay = Delay::new(14).unwrap();
+    let other = forge(&vdf, &c.base, &delay, &Integer::from(&opening.output + 1u32));
+    assert!(vdf.verify_delay(&c.base, &delay, &other));
+    assert_eq!(c.open(&other), Err(TimelockError::Decrypt));
+
+    // 'N - z' opens to the same message
+    let negated = forge(&vdf, &c.base, &delay, &Integer::from(&vdf.group.m - &opening.output));
+    assert!(vdf.verify_delay(&c.base, &delay, &negated));
+    assert_eq!(c.open(&negated).unwrap(), order.to_vec());
+
+    // a ciphertext that decodes to no committed message fails the forced opening as well
+    let mut garbled = c.clone();
+    garbled.ciphertext[0] ^= 1;
+    assert!(garbled.verify());
+    assert_eq!(garbled.open(&opening), Err(TimelockError::Decrypt));
+    assert_eq!(garbled.force_open(), Err(TimelockError::Decrypt));
+
+    println!("[Duration] force open:[{:?}]", force_duration);
+}
+
+#[test]
+fn test_verifiable_timed_signature() {
+    use bls12_381::Scalar;
+    use rug::integer::Order;
+    use vdf_snark::homomorphic::PuzzleParams;
+    use vdf_snark::timed_signature::{self, verify_signature, SigningKey};
+
+    let msg = b"settle order 42: pay 1.5 ETH";
+    let delay = Delay::new(12).unwrap();
+
+    //== Receiver side ==//
+    let vdf = vdf_snark::TrapdoorVDF::setup_with_random("2", "512");
+    let params = PuzzleParams::setup(&vdf, &delay).unwrap();
+
+    //== Signer side ==//
+    let key = SigningKey::generate();
+    let setup_lock = Instant::now();
+    let vts = timed_signature::lock(&params, &key, msg, 136, 68).unwrap();
+    let lock_duration = setup_lock.elapsed();
+
+    // C(8, 3) choices are too few to catch a cheating signer
+    assert_eq!(timed_signature::lock(&params, &key, msg, 8, 4), Err(TimelockError::WeakParameters));
+
+    //== Receiver side ==//
+    assert!(vts.verify(&params, &key.pk, msg));
+    assert!(!vts.verify(&params, &key.pk, b"settle order 43"));
+
+    let sig = vts.force(&params, &key.pk, msg).unwrap();
+    assert!(verify_signature(&key.pk, msg, &sig));
+
+    // with φ(N) an opened 'u = g^r' has other randomness 'r + φ(N)', but 'v' binds the share,
+    // so the opening cannot be turned to another share
+    let o = &vts.opened[0];
+    let share = Integer::from_digits(&(o.share + Scalar::one()).to_bytes(), Order::Lsf);
+    let randomness = Integer::from(&o.randomness + &vdf.trapdoor);
+    let forged = params.generate_with(&share, &randomness);
+    assert_eq!(forged.u, vts.puzzles[o.index].u);
+    assert_ne!(forged, vts.puzzles[o.index]);
+    let mut bad = vts.clone();
+    bad.opened[0].share += Scalar::one();
+    bad.opened[0].randomness = randomness;
+    assert!(!bad.verify(&params, &key.pk, msg));
+
+    // a signer who publishes its own 'h' instead of 'g^e' opens consistently, but only under
+    // those parameters
+    let own = PuzzleParams {
+        h: Integer::from(&params.h * 2u32) % &params.modulus,
+        ..params.clone()
+    };
+    let cheat = timed_signature::lock(&own, &key, msg, 136, 68).unwrap();
+    assert!(cheat.verify(&own, &key.pk, msg));
+    assert!(!cheat.verify(&params, &key.pk, msg));
+
+    // a commitment off the polynomial is caught
+    let mut bad = vts.clone();
+    let j = (0..136).find(|j| vts.opened.iter().all(|o| o.index != *j)).unwrap();
+    bad.commitments[j] = bad.commitments[j] + key.pk;
+    assert!(!bad.verify(&params, &key.pk, msg));
+
+    // a puzzle that does not solve to its share is skipped for the next one
+    let mut garbled = vts.clone();
+    garbled.puzzles[j].v += 1;
+    let sig = garbled.force(&params, &key.pk, msg).unwrap();
+    assert!(verify_signature(&key.pk, msg, &sig));
+
+    println!("[Duration] lock:[{:?}]", lock_duration);
+}
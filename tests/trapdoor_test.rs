@@ -41,3 +41,248 @@ fn test_generate_rsa_vdf() {
     
     println!("[Duration] setup:[{:?}], trap:[{:?}], eval:[{:?}]",setup_duration, trap_duration, eval_duration );
 }
+
+#[test]
+fn test_batch_trapdoor() {
+    use rug::Integer;
+    use vdf_snark::Delay;
+
+    //== Trader side ==//
+    let vdf = vdf_snark::TrapdoorVDF::setup_with_random("1337", "512");
+    let delay = Delay::new(14).unwrap();
+    let inputs: Vec<Integer> = (2..18u32).map(Integer::from).collect();
+
+    let setup_batch = Instant::now();
+    let res_batch = vdf.eval_batch_with_trapdoor(&inputs, &delay);
+    let batch_duration = setup_batch.elapsed();
+    let proofs = vdf.prove_batch_with_trapdoor(&inputs, &delay);
+
+    //== Operator side ==//
+    let m = vdf.group.m.clone();
+    let vdf_op = vdf_snark::TrapdoorVDF::setup("1337", m.to_string_radix(10).as_str());
+    for ((x, y), proof) in inputs.iter().zip(&res_batch).zip(&proofs) {
+        let time = delay.time().to_string();
+        assert_eq!(*y, vdf_op.eval(x.to_string().as_str(), time.as_str()));
+        assert_eq!(*y, proof.output);
+        assert!(vdf_op.verify(x.to_string().as_str(), time.as_str(), proof));
+    }
+
+    println!("[Duration] batch of {}:[{:?}]", inputs.len(), batch_duration);
+}
+
+#[test]
+fn test_eval_multi() {
+    use rug::Integer;
+    use vdf_snark::Delay;
+
+    let vdf = vdf_snark::TrapdoorVDF::setup_with_random("1337", "512");
+    let m = vdf.group.m.clone();
+    let vdf_op = vdf_snark::TrapdoorVDF::setup("1337", m.to_string_radix(10).as_str());
+    let delay = Delay::new(13).unwrap();
+    let inputs: Vec<Integer> = (2..6u32).map(Integer::from).collect();
+
+    let setup_multi = Instant::now();
+    let proofs = vdf_op.prove_multi(&inputs, &delay, 64);
+    let multi_duration = setup_multi.elapsed();
+
+    assert_eq!(vdf_op.eval_multi(&inputs, &delay), vdf.eval_batch_with_trapdoor(&inputs, &delay));
+    for (x, proof) in inputs.iter().zip(&proofs) {
+        assert!(vdf_op.verify(x.to_string().as_str(), "13", proof));
+        assert_eq!(*proof, vdf_op.prove(x.to_string().as_str(), "13"));
+    }
+
+    println!("[Duration] multi of {}:[{:?}]", inputs.len(), multi_duration);
+}
+
+#[test]
+fn test_prove_with_checkpoints() {
+    use bellman_bignat::group::RsaGroup;
+    use rug::Integer;
+    use vdf_snark::wesolowski::{self, CheckpointParams, CheckpointProver};
+    use vdf_snark::Delay;
+
+    let vdf = vdf_snark::TrapdoorVDF::setup("2", RSA_2048);
+    let x = Integer::from(5u32);
+
+    let setup_prove = Instant::now();
+    let proof = vdf.prove_with_checkpoints(&x, &Delay::new(14).unwrap(), 64);
+    let prove_duration = setup_prove.elapsed();
+    assert_eq!(proof, vdf.prove("5", "14"));
+
+    let g = RsaGroup::from_strs("2", RSA_2048);
+    let e = Integer::from(1u32) << 3000u32;
+    for &(k, gamma) in &[(1, 1), (3, 2), (5, 3)] {
+        let prover = CheckpointProver::eval(&g, &x, &e, CheckpointParams { k: k, gamma: gamma });
+        let proof = prover.prove();
+        assert!(wesolowski::verify(&g, &x, &[e.clone()], &proof));
+    }
+
+    println!("[Duration] checkpoint prove:[{:?}]", prove_duration);
+}
+
+#[test]
+fn test_verify_batch() {
+    use rug::Integer;
+    use vdf_snark::wesolowski;
+    use vdf_snark::Delay;
+
+    //== Trader side ==//
+    let vdf = vdf_snark::TrapdoorVDF::setup_with_random("1337", "512");
+    let delay = Delay::new(13).unwrap();
+    let inputs: Vec<Integer> = (2..42u32).map(Integer::from).collect();
+    let proofs = vdf.prove_batch_quotient(&inputs, &delay);
+    let plain_proofs = vdf.prove_batch_with_trapdoor(&inputs, &delay);
+
+    //== Operator side ==//
+    let m = vdf.group.m.clone();
+    let vdf_op = vdf_snark::TrapdoorVDF::setup("1337", m.to_string_radix(10).as_str());
+    let xs = vdf_op.exponent(&delay);
+    let mut plain: Vec<_> = inputs.iter().cloned().zip(plain_proofs).collect();
+    let mut items: Vec<_> = inputs.into_iter().zip(proofs).collect();
+
+    let setup_verify = Instant::now();
+    assert_eq!(wesolowski::verify_batch(&vdf_op.group, &xs, &items), Ok(()));
+    let verify_duration = setup_verify.elapsed();
+    let mut negated = items.clone();
+
+    items[3].1.pi += 1u32;
+    items[17].1.output = items[18].1.output.clone();
+    let err = wesolowski::verify_batch(&vdf_op.group, &xs, &items).unwrap_err();
+    assert_eq!(err.invalid, vec![3, 17]);
+
+    // '-y' with a valid proof for its own challenge: 'pi^l * x^r' is y, which is -(-y). The
+    // forged output is non-canonical when the true one is, so it is checked on its own
+    let e = xs.iter().fold(Integer::from(1u32), |acc, f| acc * f);
+    let i = (0..negated.len())
+        .find(|&i| {
+            let y = negated[i].0.clone().pow_mod(&e, &m).unwrap();
+            wesolowski::canonical(&vdf_op.group, &y) == y
+        })
+        .unwrap();
+    let x = negated[i].0.clone();
+    let output = Integer::from(&m - &negated[i].1.output);
+    let l = wesolowski::challenge(&vdf_op.group, &x, &output);
+    let pi = x.clone().pow_mod(&(e / l), &m).unwrap();
+    negated[i].1 = wesolowski::Proof {
+        output: output,
+        pi: pi,
+    };
+    for _ in 0..8 {
+        let err = wesolowski::verify_batch(&vdf_op.group, &xs, &negated).unwrap_err();
+        assert_eq!(err.invalid, vec![i]);
+    }
+
+    // proofs of the plain 'x^e', canonical or not, verify as well
+    assert!(plain.iter().any(|(_, proof)| wesolowski::canonical(&vdf_op.group, &proof.output) != proof.output));
+    for _ in 0..8 {
+        assert_eq!(wesolowski::verify_batch(&vdf_op.group, &xs, &plain), Ok(()));
+    }
+    plain[7].1.output += 1;
+    assert_eq!(wesolowski::verify_batch(&vdf_op.group, &xs, &plain).unwrap_err().invalid, vec![7]);
+
+    println!("[Duration] batch verify of {}:[{:?}]", items.len(), verify_duration);
+}
+
+#[test]
+fn test_aggregate_proof() {
+    use rug::Integer;
+    use vdf_snark::Delay;
+
+    //== Trader side ==//
+    let vdf = vdf_snark::TrapdoorVDF::setup_with_random("1337", "512");
+    let delay = Delay::new(13).unwrap();
+    let inputs: Vec<Integer> = (2..34u32).map(Integer::from).collect();
+    let mut outputs: Vec<Integer> = vdf
+        .eval_batch_with_trapdoor(&inputs, &delay)
+        .iter()
+        .map(|y| vdf_snark::wesolowski::canonical(&vdf.group, y))
+        .collect();
+    let proof = vdf.prove_aggregate(&inputs, &outputs, &delay);
+
+    // with only φ(N) known the proof is the same
+    let mut vdf_phi = vdf_snark::TrapdoorVDF::setup("1337", vdf.group.m.to_string_radix(10).as_str());
+    vdf_phi.trapdoor = vdf.trapdoor.clone();
+    assert_eq!(vdf_phi.prove_aggregate(&inputs, &outputs, &delay), proof);
+
+    //== Operator side ==//
+    let m = vdf.group.m.clone();
+    let vdf_op = vdf_snark::TrapdoorVDF::setup("1337", m.to_string_radix(10).as_str());
+    assert!(vdf_op.verify_aggregate(&inputs, &outputs, &delay, &proof));
+    assert_eq!(vdf_op.prove_aggregate(&inputs, &outputs, &delay), proof);
+
+    // 'm - y_i' with a proof made for it would pass the RSA group check whenever alpha_i is even
+    let mut negated = outputs.clone();
+    negated[4] = Integer::from(&m - &negated[4]);
+    let forged = vdf.prove_aggregate(&inputs, &negated, &delay);
+    assert!(!vdf_op.verify_aggregate(&inputs, &negated, &delay, &forged));
+
+    outputs.swap(0, 1);
+    assert!(!vdf_op.verify_aggregate(&inputs, &outputs, &delay, &proof));
+}
+
+#[test]
+fn test_threshold_trapdoor() {
+    use rug::Integer;
+    use vdf_snark::threshold::{self, ThresholdError};
+    use vdf_snark::Delay;
+
+    //== Dealer ==//
+    let vdf = vdf_snark::TrapdoorVDF::setup_with_safe_primes("2", "256");
+    let delay = Delay::new(14).unwrap();
+    let (params, shares) = threshold::deal(&vdf, &delay, 5, 3).unwrap();
+    assert_eq!(
+        threshold::deal(&vdf_snark::TrapdoorVDF::setup("2", RSA_2048), &delay, 5, 3),
+        Err(ThresholdError::MissingTrapdoor)
+    );
+    assert_eq!(threshold::deal(&vdf, &delay, 5, 1), Err(ThresholdError::InvalidThreshold));
+
+    //== Operators ==//
+    let x = Integer::from(1337u32);
+    let partials: Vec<_> = shares.iter().map(|s| s.partial_eval(&params, &x)).collect();
+    for partial in &partials {
+        assert!(params.verify_partial(&x, partial));
+    }
+
+    let setup_combine = Instant::now();
+    let y = params.combine(&x, &partials[2..]).unwrap();
+    let combine_duration = setup_combine.elapsed();
+
+    assert_eq!(y, vdf.eval_delay(&x, &delay));
+    assert_eq!(params.combine(&x, &partials[..2]), Err(ThresholdError::NotEnoughShares));
+
+    let mut forged = partials[0].clone();
+    forged.value += 1u32;
+    assert_eq!(params.combine(&x, &[forged, partials[1].clone(), partials[2].clone()]), Err(ThresholdError::InvalidPartial(1)));
+
+    // D is odd for about half of the moduli; squares and non-squares both combine to x^D
+    let mut odd = 0;
+    for _ in 0..16 {
+        let vdf = vdf_snark::TrapdoorVDF::setup_with_safe_primes("2", "256");
+        let (params, shares) = threshold::deal(&vdf, &delay, 4, 2).unwrap();
+        odd += params.parity;
+        for x in [Integer::from(1337u32), Integer::from(&vdf.group.m - 1337u32)] {
+            let partials: Vec<_> = shares.iter().map(|s| s.partial_eval(&params, &x)).collect();
+            assert_eq!(params.combine(&x, &partials[1..3]).unwrap(), vdf.eval_delay(&x, &delay));
+        }
+    }
+    println!("odd D in {} of 16 dealings", odd);
+
+    println!("[Duration] combine:[{:?}]", combine_duration);
+}
+
+#[test]
+fn test_delay_range() {
+
+    use std::str::FromStr;
+    use vdf_snark::{Delay, DelayError};
+
+    assert_eq!(Delay::new(30).unwrap(), Delay::from_str("30").unwrap());
+    // past TIME_MAX, negative or not a number
+    assert!(matches!(Delay::new(31), Err(DelayError::OutOfRange)));
+    assert!(matches!(Delay::from_str("-1"), Err(DelayError::OutOfRange)));
+    assert!(matches!(Delay::from_str("twelve"), Err(DelayError::Parse(_))));
+
+    // the '&str' API clamps the time as it always did
+    let vdf = vdf_snark::TrapdoorVDF::setup_with_random("2", "256");
+    assert_eq!(vdf.eval_with_trapdoor("2", "31"), vdf.eval_with_trapdoor("2", "30"));
+}